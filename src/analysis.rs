@@ -1,30 +1,75 @@
 use std::collections::{HashMap, HashSet};
+use std::io::BufReader;
 
 use anyhow::{Context, Result};
 
+use crate::cli::Preset;
 use crate::constants::*;
-use crate::util::Util;
+use crate::input::Input;
+use crate::readers::Readers;
+use crate::tokenizer::Tokenizer;
 
 #[derive(Debug)]
 pub struct RawData {
     pub freqs: HashMap<String, u64>,
     pub sentences: Vec<String>,
+    /// Where every sentence is located in its source, for the formats that have such a thing (subtitle timestamps etc.). Either empty or as long as `sentences`, with empty locations for the formats that have no such thing.
+    pub locations: Vec<String>,
     pub lang: String,
 }
 
 impl RawData {
-    pub fn from_file(filename: &str, lang: String) -> Result<RawData> {
-        let sentences = Util::sentences_from_file(filename).context(format!(
-            "While getting frequencies from file `{}`.",
-            filename
-        ))?;
-        let freqs = Self::collect_freqs(&sentences);
-
-        Ok(Self {
-            freqs,
-            sentences,
+    pub fn new(lang: String) -> Self {
+        Self {
+            freqs: HashMap::new(),
+            sentences: vec![],
+            locations: vec![],
             lang,
-        })
+        }
+    }
+
+    /// Reads and analyzes a whole input, see `RawData::read_input()`.
+    /// @returns - the data, and the blake3 hash of the input's (decompressed) content.
+    pub fn from_input(input: &Input, lang: String, preset: Preset) -> Result<(RawData, [u8; 32])> {
+        let mut data = Self::new(lang.clone());
+        let content_hash = Self::read_input(input, &lang, preset, &mut |sentence, location| {
+            data.push(sentence, location);
+            Ok(())
+        })?;
+
+        Ok((data, content_hash))
+    }
+
+    /// Streams the sentences of an input, see `Readers::sentences()`.
+    /// @returns - the blake3 hash of the input's (decompressed) content.
+    pub fn read_input(
+        input: &Input,
+        lang: &str,
+        preset: Preset,
+        on_sentence: &mut dyn FnMut(String, String) -> Result<()>,
+    ) -> Result<[u8; 32]> {
+        let mut reader = input.open()?;
+        Readers::sentences(
+            &mut BufReader::new(&mut reader),
+            input.format(),
+            preset,
+            lang,
+            input.path(),
+            on_sentence,
+        )
+        .context(format!("While getting frequencies from `{}`.", input))?;
+
+        reader.finalize()
+    }
+
+    /// Adds a single sentence, counting its words.
+    pub fn push(&mut self, sentence: String, location: String) {
+        // NOTE: every occurrence of a word counts exactly once. Before training was streamed in batches, a word's count started at 1 before its first occurrence was added, so the frequencies in databases trained back then are one too high for every word of every trained text. They aren't corrected automatically, as there's no telling which texts were counted that way: train such a database again from scratch to get exact counts.
+        for word in Tokenizer::new(&self.lang).words(&sentence) {
+            *self.freqs.entry(word).or_insert(0) += 1;
+        }
+        self.sentences.push(sentence);
+        self.locations.push(location);
     }
 
     pub fn from_preexisting_data(
@@ -35,6 +80,7 @@ impl RawData {
         Self {
             freqs,
             sentences,
+            locations: vec![],
             lang,
         }
     }
@@ -43,23 +89,6 @@ impl RawData {
     pub fn data_sizes(&self) -> (usize, usize) {
         (self.freqs.len(), self.sentences.len())
     }
-
-    fn collect_freqs(sentences: &[String]) -> HashMap<String, u64> {
-        let mut freqs = HashMap::new();
-        for s in sentences {
-            let words: Vec<String> = s
-                .split_whitespace()
-                .map(|s| Util::clean_token(s, &GENERIC_WORD_GARBAGE_PATTERNS))
-                .filter(|s| !s.is_empty())
-                .collect();
-            for word in words {
-                let entry = freqs.entry(word).or_insert(1);
-                *entry += 1;
-            }
-        }
-
-        freqs
-    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -95,15 +124,11 @@ impl SentenceRanker {
 
     pub fn rank_sentence(
         sentence: &str,
+        tokenizer: &Tokenizer,
         db_freqs: &HashMap<String, u64>,
         dup_checker: Option<&mut HashSet<Vec<String>>>,
     ) -> Option<Rank> {
-        let mut total_freq: u64 = 0;
-        let words: Vec<String> = sentence
-            .split_whitespace()
-            .map(|s| Util::clean_token(s, &GENERIC_WORD_GARBAGE_PATTERNS))
-            .filter(|s| !s.is_empty())
-            .collect();
+        let words = tokenizer.words(sentence);
         let words_clone = words.clone();
 
         // Discard current sentence from ranking (still counts in the word frequencies DB though) if number of words in sentence is smaller than threshold.
@@ -111,18 +136,7 @@ impl SentenceRanker {
             return None;
         }
 
-        for word in &words {
-            total_freq += db_freqs[word];
-        }
-
-        // NOTE: the idea here is to have a weighted penalty to easiness (making the sentence harder in the ranking) for the high word count.
-        // The penalty is not just weighted, it's also exponential. Meaning the penalty gets exponentially higher the more words the sentence has.
-        let word_count = words.len() as u64;
-        // Average frequency divided by a word count penalty
-        let avg_freq = total_freq / word_count;
-        let penalty_factor = EXP_WORD_COUNT_PENALTY_FACTOR;
-        let word_penalty = (word_count as f64).powf(penalty_factor); // Exponential penalty for length
-        let score = (avg_freq as f64 / word_penalty).round() as u64;
+        let score = Self::score_words(&words, db_freqs);
 
         let maybe_new_rank = Rank::new(sentence.to_owned(), score, words_clone);
         if let Some(dc) = dup_checker {
@@ -137,6 +151,31 @@ impl SentenceRanker {
         }
     }
 
+    /// Calculates the "easiness" score of an already split sentence from the frequencies of its words.
+    /// Words missing from `db_freqs` count as a frequency of 0.
+    /// @param words - the cleaned up words of the sentence, see `Tokenizer::words()`.
+    /// @param db_freqs - word frequencies to score the words against, has to contain every word in `words`.
+    /// @returns - the score, the bigger the easier. 0 for an empty word slice.
+    pub fn score_words(words: &[String], db_freqs: &HashMap<String, u64>) -> u64 {
+        if words.is_empty() {
+            return 0;
+        }
+
+        let mut total_freq: u64 = 0;
+        for word in words {
+            total_freq = total_freq.saturating_add(db_freqs.get(word).copied().unwrap_or(0));
+        }
+
+        // NOTE: the idea here is to have a weighted penalty to easiness (making the sentence harder in the ranking) for the high word count.
+        // The penalty is not just weighted, it's also exponential. Meaning the penalty gets exponentially higher the more words the sentence has.
+        let word_count = words.len() as u64;
+        // Average frequency divided by a word count penalty
+        let avg_freq = total_freq / word_count;
+        let penalty_factor = EXP_WORD_COUNT_PENALTY_FACTOR;
+        let word_penalty = (word_count as f64).powf(penalty_factor); // Exponential penalty for length
+        (avg_freq as f64 / word_penalty).round() as u64
+    }
+
     // TODO[[13]]: develop the ability to test-rank and test-frequency-profile a single sentence, according to the current frequency values in the database and the ranking formula.
     /// This method ranks all sentences based on their "easiness" rating.
     /// "easiness" is calculated as `(arithmetic average of all the word frequencies in the sentence) / (penalty for long sentence)`. Read about the penalty in the note inside the function body.
@@ -144,16 +183,20 @@ impl SentenceRanker {
     fn rank(data: &RawData) -> Vec<Rank> {
         let mut rankings = vec![];
         let mut duplicate_checker: HashSet<Vec<String>> = HashSet::new();
+        let tokenizer = Tokenizer::new(&data.lang);
 
         for sentence in &data.sentences {
-            if let Some(r) =
-                SentenceRanker::rank_sentence(sentence, &data.freqs, Some(&mut duplicate_checker))
-            {
+            if let Some(r) = SentenceRanker::rank_sentence(
+                sentence,
+                &tokenizer,
+                &data.freqs,
+                Some(&mut duplicate_checker),
+            ) {
                 rankings.push(r);
             }
         }
 
-        rankings.sort_by(|a, b| a.score.cmp(&b.score));
+        rankings.sort_by_key(|r| r.score);
         rankings
     }
 }
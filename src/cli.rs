@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 
+use crate::constants::DEFAULT_BATCH_NAME;
+
 pub const FREQSAGE_ABOUT_SHORT: &str = "Frequency analysis of text for language learning!";
 pub const FREQSAGE_ABOUT: &str = "FreQ Sage is an application for frequency analysis of text, mostly for the purpose of language learning and, specifically, sentence mining.";
 
@@ -15,34 +17,108 @@ pub const FREQSAGE_ABOUT: &str = "FreQ Sage is an application for frequency anal
 pub struct CLI {
     #[command(subcommand)]
     pub command: Commands,
-    // TODO: develop a system for processing texts based on a number of pre-existing presets for specific text sources: such as Gutenberg books, Wikipedia articles, etc.
-    // TODO: [!!!] Introduce and develop a new argument that controls starting from a specific rank (only start from the 10th sentence, for example)
-    // TODO[[2]] [!!!]: develop a feature (called `random` or `topN`, or `batch`) that shows N random sentences. There are two sub-modes for this:
-    // 1. Have a feature to restrict sentences to showing only random sentences with easiness of up-to-X (not-harder-than-X-easiness). This is done so that we can limit the application from showing sentences that are too complex for the current level.
-    // 2. Have a feature of only showing new sentences on subsequent runs of the command. Even though the sentences are random, only show those that haven't been shown on previous runs.
-    // 3. Have a feature to regen only specific sentences out of the N generated ones. For example, if 20 sentences have been generated and you only don't like sentences 3, 7 and 15, you need to be able to specify that you only want those to be regenerated, and that's it. Other 17 should remain as is.
-    // 4. Have a feature to print those final regenerated and now-suitable N sentences to a new file.
+    #[arg(
+        global = true,
+        long = "lang",
+        help = "The language to work with. NOTE: sentences/frequencies for different languages are stored in different database files (English.redb, Finnish.redb etc.), so this also selects the database file. It also picks the abbreviations (`Dr.`, `z.B.`) that don't end a sentence, see data/abbreviations, and how elided and hyphenated words (`l'homme`, `dit-il`) are split. If left unspecified, the value of DEFAULT_LANGUAGE env variable is used, usually English. Can be changed in .env (see .env.template)."
+    )]
+    pub lang: Option<String>,
+    #[arg(
+        global = true,
+        long = "db",
+        help = "Use this specific database file instead of the one named after the language in the data directory (see the `list` command). `--lang` still determines the language the database is treated as."
+    )]
+    pub db: Option<PathBuf>,
+    #[arg(
+        global = true,
+        long = "create",
+        action = ArgAction::SetTrue,
+        help = "Create the database if it doesn't exist yet. Without this flag, using a database that doesn't exist is an error, so that a typo in `--lang`/`--db` never silently creates a new empty database."
+    )]
+    pub create: bool,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     #[command(
-        about = "FreQ Sage prints out status of the default DB file + the number of frequencies and sentence rankings currently in the default DB>."
+        about = "FreQ Sage prints out status of the DB file (see `--lang` and `--db`) + the number of frequencies and sentence rankings currently in it."
+    )]
+    Status,
+    #[command(
+        about = "List the language databases found in the data directory. The data directory is set by the FREQSAGE_DATA_DIR env variable, by default it's the platform's data directory (e.g. ~/.local/share/freq-sage)."
+    )]
+    List,
+    #[command(
+        about = "Dry-run: analyze a text and show its word frequencies and sentence rankings WITHOUT adding anything to the database. Useful for checking whether a text is worth training on and how hard it is."
     )]
-    Status {
+    Analyze {
+        #[arg(help = "The text file to analyze, `-` for the standard input [REQUIRED].")]
+        file: PathBuf,
+        #[arg(
+            value_enum,
+            short = 'p',
+            long = "preset",
+            default_value_t = Preset::Plain,
+            help = "The source the text comes from, to clean up the parts of it that aren't the text itself."
+        )]
+        preset: Preset,
+        #[arg(
+            short = 'd',
+            long = "against-db",
+            action = ArgAction::SetTrue,
+            help = "Score the sentences against the word frequencies already in the database instead of the text's own word counts, and show how much of the text the database already covers. The database is only read, never written to, and has to exist."
+        )]
+        against_db: bool,
         #[arg(
             short = 'l',
-            long = "lang",
-            help = "Specify the language to check the status for. NOTE: sentences/frequencies for different languages are stored in different database files, therefore you can only see the status of one language (database) at a time. !!WARNING!!: creates a new empty database file if the .redb file for the specified language doesn't exist - don't specify a non-existant language in this argument if you don't want the file created."
+            long = "limit",
+            help = "If specified, limits the shown frequencies and rankings to this specific amount each. If left unspecified, the value of DEFAULT_TOP_N_LIMIT env variable is used, usually 50. Can be changed in .env (see .env.template)."
         )]
-        language: Option<String>,
+        limit: Option<u32>,
+        #[arg(
+            conflicts_with = "limit",
+            short = 'n',
+            long = "no-limit",
+            action = ArgAction::SetTrue,
+            help = "The opposite (and obviously conflicts with) of `-l/--limit`. If this is specified, every frequency and ranking of the text is printed."
+        )]
+        no_limit: bool,
     },
     #[command(about = "Train FreQSage by giving it a text to analyze.")]
     Train {
-        #[arg(help = "The text file to analyze [REQUIRED].")]
-        file: PathBuf,
+        #[arg(
+            required = true,
+            help = "The files or directories to train on, `-` for the standard input. Directories are walked recursively, skipping hidden files and the files that can't be decoded; gzip, zstd and bzip2 compressed files are decompressed on the fly. All of the inputs are trained on in a single run: if one of them fails, none of them are trained on [REQUIRED]."
+        )]
+        inputs: Vec<PathBuf>,
+        #[arg(
+            short = 'i',
+            long = "include",
+            help = "Only train on the files in the directories that match this glob (e.g. `*.srt` or `books/**/*.epub`), relative to the directory. Can be given several times."
+        )]
+        include: Vec<String>,
+        #[arg(
+            short = 'x',
+            long = "exclude",
+            help = "Skip the files in the directories that match this glob, relative to the directory. Can be given several times."
+        )]
+        exclude: Vec<String>,
+        #[arg(
+            value_enum,
+            short = 'p',
+            long = "preset",
+            default_value_t = Preset::Plain,
+            help = "The source the text comes from, to clean up the parts of it that aren't the text itself."
+        )]
+        preset: Preset,
+        #[arg(
+            short = 'f',
+            long = "force",
+            action = ArgAction::SetTrue,
+            help = "Train on an input even if an input with exactly the same content has already been trained on. WARNING: this counts all the words of the text twice."
+        )]
+        force: bool,
     },
-    // TODO: develop a Show flag to show N frequencies or sentence rankings with an offset: not just the ones with the highest rating, but also e.g. from number 200 and below, etc. (the compliment of --limit: --limit determines how many rankings to show, this determines which ranking to show them from).
     #[command(about = "Show top word frequencies or sentence rankings.")]
     Show {
         #[arg(value_enum, help = "what to show: sentences or rankings.")]
@@ -61,6 +137,227 @@ pub enum Commands {
             help = "The opposite (and obviously conflicts with) of `-l/--limit`. If this is specified, no limit is applied to the top queries at all - no .env variable, no flag, no default, nothing. This will print as many entries as there are in the database. WARNING: may print A LOT if you've been using your DB for a while."
         )]
         no_limit: bool,
+        #[arg(
+            short = 'o',
+            long = "offset",
+            default_value_t = 0,
+            help = "The complement of `-l/--limit`: how many entries to skip before starting to show them. `--limit` determines how many entries to show, this determines where to start showing them from. When combined with `--at-most`, the entries are skipped from the first one within the bounds."
+        )]
+        offset: u64,
+        #[arg(
+            conflicts_with = "offset",
+            short = 'r',
+            long = "from-rank",
+            help = "Same as `-o/--offset`, but 1-based: start showing entries from this rank, `--from-rank 200` is the same as `--offset 199`."
+        )]
+        from_rank: Option<u64>,
+        #[arg(
+            long = "at-most",
+            help = "Only show entries whose frequency (or sentence rating) is at most this value. Jumps straight to those entries instead of paging through the more frequent/easier ones."
+        )]
+        at_most: Option<u64>,
+        #[arg(
+            long = "at-least",
+            help = "Only show entries whose frequency (or sentence rating) is at least this value."
+        )]
+        at_least: Option<u64>,
+        #[arg(
+            short = 's',
+            long = "sources",
+            action = ArgAction::SetTrue,
+            help = "Also show which trained files every sentence came from. Ignored for frequencies."
+        )]
+        sources: bool,
+    },
+    #[command(about = "List every file the database has been trained on.")]
+    Sources,
+    #[command(
+        about = "Remove everything a trained file has contributed to the database: its word counts, and the sentences no other trained file contains. The remaining sentences are rescored. NOTE: only the files trained since sources started being recorded can be untrained (see the `sources` command)."
+    )]
+    Untrain {
+        #[arg(
+            help = "The source to untrain: either its id from the `sources` command, or the path of the trained file. If the same path has been trained several times, the latest training is removed [REQUIRED]."
+        )]
+        source: String,
+    },
+    #[command(
+        about = "Mark words as known, unknown or ignored. Sentences with exactly one unknown word can then be shown with `show i+1`."
+    )]
+    Mark {
+        #[arg(value_enum, help = "The status to mark the words with.")]
+        status: WordStatus,
+        #[arg(
+            help = "The words to mark. Cleaned up the same way as during training, so punctuation around them doesn't matter, and matched in any case: `the` is `The` too."
+        )]
+        words: Vec<String>,
+        #[arg(
+            short = 'f',
+            long = "file",
+            help = "A text file with more words to mark, separated by whitespace or newlines. Can be used together with the words given directly."
+        )]
+        file: Option<PathBuf>,
+    },
+    #[command(
+        about = "Export the easiest sentences into a file for flashcard apps. By default exports top sentence rankings, see `--word` and `--i-plus-one` for other selections."
+    )]
+    Export {
+        #[arg(value_enum, help = "The format to export into.")]
+        format: ExportFormat,
+        #[arg(
+            help = "The file to write the export into. Overwritten if it already exists [REQUIRED]."
+        )]
+        output: PathBuf,
+        #[arg(
+            short = 'w',
+            long = "word",
+            help = "Only export the sentences containing this word. The word becomes the target word of the cards."
+        )]
+        word: Option<String>,
+        #[arg(
+            conflicts_with = "word",
+            short = 'i',
+            long = "i-plus-one",
+            action = ArgAction::SetTrue,
+            help = "Only export the sentences with exactly one unknown word (see the `mark` command). The unknown word becomes the target word of the cards."
+        )]
+        i_plus_one: bool,
+        #[arg(
+            value_enum,
+            short = 'f',
+            long = "fields",
+            value_delimiter = ',',
+            default_values_t = [AnkiField::Sentence, AnkiField::Target, AnkiField::Rating],
+            help = "Comma-separated list of the fields (columns) every card consists of, in this order."
+        )]
+        fields: Vec<AnkiField>,
+        #[arg(
+            short = 't',
+            long = "tags",
+            value_delimiter = ',',
+            help = "Comma-separated list of Anki tags to add to every card."
+        )]
+        tags: Vec<String>,
+        #[arg(
+            short = 'l',
+            long = "limit",
+            help = "If specified, limits the number of exported sentences to this specific amount. If left unspecified, the value of DEFAULT_TOP_N_LIMIT env variable is used, usually 50. Can be changed in .env (see .env.template)."
+        )]
+        limit: Option<u32>,
+        #[arg(
+            conflicts_with = "limit",
+            short = 'n',
+            long = "no-limit",
+            action = ArgAction::SetTrue,
+            help = "The opposite (and obviously conflicts with) of `-l/--limit`. If this is specified, every selected sentence in the database is exported."
+        )]
+        no_limit: bool,
+    },
+    #[command(about = "Show N random sentences from the database.")]
+    Random {
+        #[arg(
+            short = 'c',
+            long = "count",
+            help = "How many random sentences to show. If left unspecified, the value of DEFAULT_TOP_N_LIMIT env variable is used, usually 50. Can be changed in .env (see .env.template)."
+        )]
+        count: Option<u32>,
+        #[arg(
+            short = 'e',
+            long = "min-easiness",
+            help = "Only draw the sentences with a rating (easiness) of at least this value, i.e. not harder than this. Limits the draw to the sentences that aren't too complex for the current level."
+        )]
+        min_easiness: Option<u64>,
+        #[arg(
+            short = 'u',
+            long = "unseen",
+            action = ArgAction::SetTrue,
+            help = "Only draw the sentences that haven't been shown by previous `random --unseen` runs, and remember the ones drawn now as shown. The history is kept in the database."
+        )]
+        unseen: bool,
+        #[arg(
+            long = "forget-seen",
+            action = ArgAction::SetTrue,
+            help = "Forget the history of shown sentences before drawing, so that every sentence can be drawn by `--unseen` again."
+        )]
+        forget_seen: bool,
+        #[arg(
+            short = 'b',
+            long = "batch",
+            default_value = DEFAULT_BATCH_NAME,
+            help = "The name to save the drawn sentences under, so that the batch can be refined later with the `batch` command. Overwrites the previous batch with the same name."
+        )]
+        batch: String,
+    },
+    #[command(
+        about = "Show, refine and save a batch of sentences previously drawn by the `random` command."
+    )]
+    Batch {
+        #[arg(
+            default_value = DEFAULT_BATCH_NAME,
+            help = "The name of the batch, the one given to `random --batch`."
+        )]
+        name: String,
+        #[arg(
+            short = 'r',
+            long = "regen",
+            value_delimiter = ',',
+            help = "Comma-separated positions (as shown in the batch, starting from 1) of the sentences to replace with fresh random ones, e.g. `--regen 3,7,15`. The rest of the batch stays as is."
+        )]
+        regen: Vec<usize>,
+        #[arg(
+            short = 's',
+            long = "save",
+            help = "Write the (refined) batch into this file, one sentence per line. Overwrites the file if it exists."
+        )]
+        save: Option<PathBuf>,
+    },
+    #[command(
+        about = "Clean up the database: remove every sentence (and optionally every word) matching a regex, e.g. boilerplate left over from web pages. Try it with `--dry-run` first!"
+    )]
+    Clean {
+        #[arg(help = "The regex to match the sentences (and words) against [REQUIRED].")]
+        pattern: String,
+        #[arg(
+            short = 'w',
+            long = "words",
+            action = ArgAction::SetTrue,
+            help = "Also remove the words matching the regex from the word frequencies. The sentences containing those words are rescored as if the words had a frequency of 0."
+        )]
+        words: bool,
+        #[arg(
+            short = 'd',
+            long = "dry-run",
+            action = ArgAction::SetTrue,
+            help = "Only show what would be removed, without touching the database."
+        )]
+        dry_run: bool,
+    },
+    #[command(
+        about = "Search the database for sentences containing a word, an exact phrase or matching a regex, the easiest sentences first."
+    )]
+    Search {
+        #[arg(
+            value_enum,
+            help = "How to search: by a single word, by an exact phrase or by a regex."
+        )]
+        how: SearchType,
+        #[arg(
+            help = "The word, phrase or regex to search for. Words and phrases are cleaned up the same way as during training, regexes are matched against the raw sentences [REQUIRED]."
+        )]
+        query: String,
+        #[arg(
+            short = 'l',
+            long = "limit",
+            help = "If specified, limits the search results to this specific amount. If left unspecified, the value of DEFAULT_TOP_N_LIMIT env variable is used, usually 50. Can be changed in .env (see .env.template)."
+        )]
+        limit: Option<u32>,
+        #[arg(
+            conflicts_with = "limit",
+            short = 'n',
+            long = "no-limit",
+            action = ArgAction::SetTrue,
+            help = "The opposite (and obviously conflicts with) of `-l/--limit`. If this is specified, every matching sentence in the database is printed."
+        )]
+        no_limit: bool,
     },
 }
 
@@ -70,4 +367,73 @@ pub enum ShowType {
     Frequencies,
     #[value(help = "Show top N sentence rankings.")]
     Rankings,
+    #[value(
+        name = "i+1",
+        help = "Show top N sentence rankings that contain exactly one unknown word (see the `mark` command)."
+    )]
+    IPlusOne,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum Preset {
+    #[value(help = "Plain text, used as is.")]
+    Plain,
+    #[value(
+        help = "A Project Gutenberg plain text book: the license, header, table of contents, headings and transcriber's notes are dropped, hard-wrapped lines are rejoined."
+    )]
+    Gutenberg,
+    #[value(
+        help = "Wikipedia: a `pages-articles.xml(.bz2)` dump (streamed article by article), a saved article page or an article's wikitext. Templates, infoboxes, tables, references, headings and lists are dropped."
+    )]
+    Wikipedia,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum ExportFormat {
+    #[value(help = "A tab-separated text file that can be imported into Anki with File -> Import.")]
+    Anki,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum AnkiField {
+    #[value(help = "The sentence itself.")]
+    Sentence,
+    #[value(
+        help = "The word the card is about: the searched word or the only unknown word of an i+1 sentence. Empty for plain rankings."
+    )]
+    Target,
+    #[value(
+        help = "The sentence with the target word turned into an Anki cloze deletion: {{c1::word}}."
+    )]
+    Cloze,
+    #[value(help = "The easiness rating of the sentence.")]
+    Rating,
+    #[value(help = "The language of the database the sentence comes from.")]
+    Language,
+    #[value(help = "The trained file(s) the sentence comes from.")]
+    Source,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum SearchType {
+    #[value(help = "Find sentences containing the word.")]
+    Word,
+    #[value(help = "Find sentences containing the exact sequence of words.")]
+    Phrase,
+    #[value(help = "Find sentences whose raw text matches the regex.")]
+    Regex,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum WordStatus {
+    #[value(help = "The word is known and doesn't need to be learned.")]
+    Known,
+    #[value(
+        help = "The word is unknown. This is the status of every word that has never been marked."
+    )]
+    Unknown,
+    #[value(
+        help = "The word isn't worth learning (names, interjections etc.), but doesn't count as unknown either."
+    )]
+    Ignored,
 }
@@ -1,11 +1,16 @@
+use std::collections::{HashMap, HashSet};
+
 use regex::Regex;
 
 use once_cell::sync::Lazy;
 
 pub static GENERIC_SENTENCE_GARBAGE_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
     vec![
-        // 1) Trim leading punctuation/whitespace, but preserve trailing sentence punctuation
-        Regex::new(r"(?u)^[\p{P}\s]+|[\p{P}&&[^.!?;]]\s*$|\s+$").unwrap(),
+        // 1) Trim leading punctuation/whitespace, but preserve trailing sentence punctuation, and the opening/closing quotes and brackets around the sentence
+        Regex::new(
+            r#"(?u)^[[\p{P}\s]&&[^\p{Ps}\p{Pi}"'¿¡]]+|[\p{P}&&[^.!?;…。！？؟\p{Pe}\p{Pf}"']]\s*$|\s+$"#,
+        )
+        .unwrap(),
         // 2) Remove Wikipedia‐style "[123]" footnotes anywhere
         Regex::new(r"\[\d+\]").unwrap(),
         // 3) If *after* trimming the sentence is still only numbers/punctuation/space, drop it
@@ -13,21 +18,306 @@ pub static GENERIC_SENTENCE_GARBAGE_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
     ]
 });
 
-pub static GENERIC_WORD_GARBAGE_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
-    vec![
-        // Remove leading and trailing whitespace
-        Regex::new(r"(?u)^\s+|\s+$").unwrap(),
-        // Remove leading punctuation and special characters (keep only letters and numbers)
-        Regex::new(r"(?u)^[^\p{L}\p{N}]+").unwrap(),
-        // Remove trailing punctuation and special characters (keep only letters and numbers)
-        Regex::new(r"(?u)[^\p{L}\p{N}]+$").unwrap(),
-    ]
-});
-
 pub const EXP_WORD_COUNT_PENALTY_FACTOR: f64 = 0.5;
 pub const WORDS_IN_SENTENCE_DISCARD_THRESHOLD: u64 = 3;
 
 pub const DEFAULT_LANGUAGE: &str = "English";
 pub const DEFAULT_TOP_N_LIMIT: u32 = 50;
 
-pub const REDB_LAYOUT_VERSION: u8 = 1;
+pub const REDB_LAYOUT_VERSION: u8 = 3;
+
+pub const DEFAULT_BATCH_NAME: &str = "last";
+
+/// Name of the directory (inside the platform's data directory) the language databases are stored in by default.
+pub const DATA_DIR_NAME: &str = "freq-sage";
+
+// Project Gutenberg preset patterns. Gutenberg has changed the wording of its markers a few times over the years, hence the loose matching.
+pub static GUTENBERG_START_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?im)^\s*\*{3}\s*START OF (THE|THIS) PROJECT GUTENBERG E-?BOOK.*$").unwrap()
+});
+pub static GUTENBERG_END_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?im)^\s*(\*{3}\s*END OF (THE|THIS) PROJECT GUTENBERG E-?BOOK|End of (the )?Project Gutenberg).*$").unwrap()
+});
+/// Paragraphs that are Gutenberg's or the transcriber's notes rather than the book itself.
+pub static GUTENBERG_NOTE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\[?\s*(transcriber'?s?'? notes?|produced by|this e-?book was produced|e-?text prepared by|\[?illustration)").unwrap()
+});
+pub static GUTENBERG_CONTENTS_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^\s*(table of )?contents\.?\s*$").unwrap());
+/// Lines of a table of contents are short, prose in Gutenberg books is hard-wrapped at around 70 characters.
+pub const GUTENBERG_CONTENTS_MAX_LINE_LEN: usize = 55;
+
+// Wikipedia preset patterns.
+pub static WIKI_HTML_DOCUMENT_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(html|body)\b").unwrap());
+/// Comments, references and the tags whose content is never prose (formulas, galleries, code etc.).
+pub static WIKI_DROPPED_MARKUP_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    let tags = [
+        "ref",
+        "math",
+        "chem",
+        "gallery",
+        "imagemap",
+        "syntaxhighlight",
+        "source",
+        "pre",
+        "code",
+        "timeline",
+        "score",
+        "hiero",
+        "table",
+    ]
+    .map(|t| format!(r"<{t}\b[^>]*/>|<{t}\b[^>]*>.*?</{t}\s*>"));
+
+    Regex::new(&format!(r"(?is)<!--.*?-->|{}", tags.join("|"))).unwrap()
+});
+/// `[https://example.com label]`, only the label is kept.
+pub static WIKI_EXTERNAL_LINK_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[(?:https?:)?//[^\s\]]+(?:\s+([^\]]*))?\]").unwrap());
+/// Bold and italics, leftover HTML tags and magic words (__TOC__ etc.).
+pub static WIKI_FORMATTING_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"'{2,}|<[^>]*>|__[A-Z]+__").unwrap());
+/// Wikitext lines that aren't prose: headings, lists, indents and table leftovers.
+pub const WIKI_DROPPED_LINE_PREFIXES: [char; 7] = ['=', '*', '#', ':', ';', '|', '!'];
+/// HTML elements of a saved Wikipedia article that don't contain the article's prose.
+pub const WIKI_HTML_DROPPED_TAGS: [&str; 22] = [
+    "head", "script", "style", "noscript", "nav", "header", "footer", "table", "figure", "sup",
+    "math", "pre", "code", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "dl",
+];
+pub const WIKI_HTML_DROPPED_CLASSES: [&str; 16] = [
+    "infobox",
+    "navbox",
+    "vertical-navbox",
+    "sidebar",
+    "reference",
+    "reflist",
+    "references",
+    "mw-references-wrap",
+    "mw-editsection",
+    "hatnote",
+    "thumb",
+    "metadata",
+    "ambox",
+    "toc",
+    "catlinks",
+    "noprint",
+];
+/// HTML elements that start and end a block of text, their text is never a part of the same sentence as the text around them.
+pub const HTML_BLOCK_TAGS: [&str; 27] = [
+    "p",
+    "div",
+    "hr",
+    "li",
+    "ul",
+    "ol",
+    "dl",
+    "dd",
+    "dt",
+    "blockquote",
+    "section",
+    "article",
+    "aside",
+    "header",
+    "footer",
+    "nav",
+    "main",
+    "table",
+    "tr",
+    "td",
+    "th",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+pub const HTML_ENTITIES: [(&str, &str); 9] = [
+    ("&nbsp;", " "),
+    ("&ndash;", "–"),
+    ("&mdash;", "—"),
+    ("&minus;", "−"),
+    ("&quot;", "\""),
+    ("&apos;", "'"),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&amp;", "&"),
+];
+
+/// Breaks UAX #29 doesn't make by itself: after a semicolon, and after an ellipsis character (`…`, unlike `...`) followed by a capitalized word. The break comes after the whitespace of whichever group matched.
+pub static SENTENCE_EXTRA_BREAK_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(;\s+)|(…[\p{Pe}\p{Pf}"']*\s+)\p{Lu}"#).unwrap());
+/// Abbreviations that don't end a sentence, per language (the lowercase name of it, as in `--lang`), see `data/abbreviations`.
+pub const ABBREVIATION_LISTS: [(&str, &str); 9] = [
+    ("dutch", include_str!("../data/abbreviations/dutch.txt")),
+    ("english", include_str!("../data/abbreviations/english.txt")),
+    ("finnish", include_str!("../data/abbreviations/finnish.txt")),
+    ("french", include_str!("../data/abbreviations/french.txt")),
+    ("german", include_str!("../data/abbreviations/german.txt")),
+    ("italian", include_str!("../data/abbreviations/italian.txt")),
+    (
+        "portuguese",
+        include_str!("../data/abbreviations/portuguese.txt"),
+    ),
+    ("russian", include_str!("../data/abbreviations/russian.txt")),
+    ("spanish", include_str!("../data/abbreviations/spanish.txt")),
+];
+pub static ABBREVIATIONS: Lazy<HashMap<&str, HashSet<&str>>> = Lazy::new(|| {
+    ABBREVIATION_LISTS
+        .iter()
+        .map(|(lang, list)| {
+            let abbreviations = list
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect();
+            (*lang, abbreviations)
+        })
+        .collect()
+});
+/// Capital letters that are words of their own, per language, so that a sentence can end with them (`It was I. Then we left.`). Any other capital letter followed by a period is an initial.
+pub const CAPITAL_LETTER_WORDS: [(&str, &[&str]); 1] = [("english", &["I"])];
+pub static PARAGRAPH_BREAK_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\r?\n[ \t]*\r?\n").unwrap());
+/// Longest stretch of text without a sentence terminator or a paragraph break the segmenter holds on to while streaming. Anything longer isn't prose anyway, and is cut off as a sentence of its own to keep memory use bounded.
+pub const SEGMENTER_MAX_PENDING_BYTES: usize = 64 * 1024;
+
+// Word tokenizer rules, per language (the lowercase name of it, as in `--lang`).
+/// Hyphens that join the parts of a compound word (`well-known`). Dashes (`–`, `—`) separate words instead.
+pub const WORD_HYPHENS: [char; 2] = ['-', '\u{2010}'];
+/// Apostrophe look-alikes written as `'` inside words, so that `don’t` and `don't` are the same word.
+pub const WORD_APOSTROPHES: [char; 2] = ['\u{2019}', '\u{02BC}'];
+/// Scripts written without spaces between words (Thai, Lao, Myanmar, Khmer, Tai Tham, Tai Viet). UAX #29 needs a dictionary to find their words, so a run of them is kept whole instead, the way whitespace splitting always had it.
+pub const UNSPACED_SCRIPT_RANGES: [(char, char); 6] = [
+    ('\u{0E00}', '\u{0EFF}'),
+    ('\u{1000}', '\u{109F}'),
+    ('\u{1780}', '\u{17FF}'),
+    ('\u{1A20}', '\u{1AAF}'),
+    ('\u{A9E0}', '\u{AA7F}'),
+    ('\u{AA80}', '\u{AADF}'),
+];
+/// Words written with an apostrophe instead of their last vowel and glued to the next word (`l'homme`, `dell'anno`). They're split off as words of their own, apostrophe included.
+pub const ELISIONS: [(&str, &[&str]); 2] = [
+    (
+        "french",
+        &[
+            "c", "d", "j", "l", "m", "n", "s", "t", "qu", "jusqu", "lorsqu", "puisqu", "quoiqu",
+        ],
+    ),
+    (
+        "italian",
+        &[
+            "l", "un", "all", "dall", "dell", "nell", "sull", "coll", "quell", "quest", "bell",
+            "sant", "c", "d", "m", "t", "s", "v", "dev",
+        ],
+    ),
+];
+/// Pronouns written after a verb with a hyphen (`dit-il`, `dá-me`). A hyphenated compound is split into its parts only if all of them but the first are such pronouns (or euphonic letters), otherwise it's a word of its own (`peut-être`, `guarda-chuva`).
+pub const HYPHEN_CLITICS: [(&str, &[&str]); 2] = [
+    (
+        "french",
+        &[
+            "je", "tu", "il", "elle", "on", "nous", "vous", "ils", "elles", "le", "la", "les",
+            "moi", "toi", "lui", "leur", "y", "en", "ce",
+        ],
+    ),
+    (
+        "portuguese",
+        &[
+            "me", "te", "se", "o", "a", "os", "as", "lo", "la", "los", "las", "no", "na", "nos",
+            "nas", "lhe", "lhes", "vos",
+        ],
+    ),
+];
+/// Letters put between a verb and its hyphenated pronoun just for the sound of it (`va-t-on`). They're dropped, as they aren't words.
+pub const HYPHEN_EUPHONICS: [(&str, &[&str]); 1] = [("french", &["t"])];
+
+// Subtitle patterns.
+/// Styling tags: HTML-like (`<i>`, `<font>`, WebVTT's `<c.yellow>`, `<v Speaker>` and inline timestamps) and ASS/SSA override blocks (`{\i1}`, `{\an8}`).
+pub static SUBTITLE_MARKUP_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<[^>]*>|\{[^}]*\}").unwrap());
+/// Sound descriptions for the hearing impaired, such as `[DOOR CLOSES]` or `(laughs)`.
+pub static SUBTITLE_ANNOTATION_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[[^\]]*\]|\([^)]*\)").unwrap());
+/// Dialogue dashes and all-caps speaker labels at the start of a line: `- JOHN: Hi.`
+pub static SUBTITLE_SPEAKER_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:[-–—]\s*)?(?:\p{Lu}[\p{Lu}\d .'-]*:\s+)?").unwrap());
+/// The media files subtitles can belong to, looked up next to the subtitle file by its name.
+pub const MEDIA_FILE_EXTENSIONS: [&str; 8] =
+    ["mkv", "mp4", "avi", "webm", "mov", "m4v", "mp3", "m4a"];
+
+// EPUB patterns.
+/// `epub:type` (and DPUB-ARIA `role`) values of the elements that aren't a part of the book's text: front and back matter, navigation and notes.
+pub const EPUB_DROPPED_TYPES: [&str; 22] = [
+    "frontmatter",
+    "backmatter",
+    "cover",
+    "titlepage",
+    "halftitlepage",
+    "toc",
+    "landmarks",
+    "page-list",
+    "copyright-page",
+    "colophon",
+    "dedication",
+    "acknowledgments",
+    "index",
+    "bibliography",
+    "glossary",
+    "footnote",
+    "footnotes",
+    "endnote",
+    "endnotes",
+    "rearnote",
+    "rearnotes",
+    "noteref",
+];
+/// Headings aren't sentences, they're recorded as the chapter a sentence is in instead.
+pub const EPUB_DROPPED_TAGS: [&str; 15] = [
+    "head", "script", "style", "nav", "aside", "sup", "table", "figure", "pre", "h1", "h2", "h3",
+    "h4", "h5", "h6",
+];
+/// Front and back matter that is only recognizable by the file name, as many EPUBs don't mark it up.
+pub static EPUB_DROPPED_FILE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(^|[/_.-])(cover|title-?page|toc|nav|copyright|colophon|dedication|acknowledge?ments?|about-?the-?author|footnotes?|endnotes?)([_.-]|$)").unwrap()
+});
+
+// HTML patterns.
+/// Elements of a web page that aren't a part of its prose: site navigation, forms, tables, code etc.
+pub const HTML_DROPPED_TAGS: [&str; 20] = [
+    "head", "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "button", "select", "table", "pre", "figure", "svg", "iframe", "canvas", "math", "object",
+];
+pub const HTML_DROPPED_ROLES: [&str; 9] = [
+    "navigation",
+    "banner",
+    "contentinfo",
+    "complementary",
+    "search",
+    "menu",
+    "menubar",
+    "dialog",
+    "alert",
+];
+
+// PDF patterns.
+/// Page numbers on their own line: `12`, `- 12 -`, `Page 12`, `12 / 300`, `xiv`.
+pub static PDF_PAGE_NUMBER_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^[\s\-–—]*(page\s+)?(\d+|[ivxlc]+)(\s*(/|of)\s*\d+)?[\s\-–—]*$").unwrap()
+});
+/// How many lines at the top and the bottom of every page are checked for running headers and footers.
+pub const PDF_RUNNING_LINE_SPAN: usize = 2;
+/// A line is a running header or footer if it repeats at the edges of at least this many pages...
+pub const PDF_RUNNING_LINE_MIN_PAGES: usize = 3;
+/// ...and of at least this share of all pages.
+pub const PDF_RUNNING_LINE_MIN_SHARE: f64 = 0.3;
+
+/// The input path that stands for the standard input.
+pub const STDIN_INPUT: &str = "-";
+/// Extensions of the compressed files, stripped before detecting the format of the content.
+pub const COMPRESSED_FILE_EXTENSIONS: [&str; 4] = [".gz", ".zst", ".zstd", ".bz2"];
+/// Number of (decompressed) bytes read from the start of a file found in a directory walk to tell whether it can be decoded at all.
+pub const DECODABLE_SNIFF_BYTES: u64 = 8192;
+
+/// Number of sentences a training run reads before flushing their counts and rankings to the database.
+pub const TRAINING_BATCH_SENTENCES: usize = 50_000;
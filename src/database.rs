@@ -1,16 +1,20 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use blake3::Hasher;
-use redb::{Database, ReadableTableMetadata, TableDefinition};
+use redb::{Database, ReadOnlyDatabase, ReadableTableMetadata, TableDefinition, WriteTransaction};
 use redb::{ReadableDatabase, ReadableTable};
 use redb_derive::Value;
+use regex::Regex;
 
 use crate::analysis::RawData;
 /// This module is responsible for the redb key/value database that stores and represents word frequencies and sentence rankings based on specific languages.
 use crate::analysis::SentenceRanker;
-use crate::constants::REDB_LAYOUT_VERSION;
+use crate::constants::{DATA_DIR_NAME, REDB_LAYOUT_VERSION, WORDS_IN_SENTENCE_DISCARD_THRESHOLD};
+use crate::tokenizer::Tokenizer;
 use crate::util::Util;
 
 // TODO: [LATER] Develop the ability to sync database from/to some external "cloud" source for quick fetch on a different machine. Potentially copy into a cloud folder or push/pull to/from GitHub.
@@ -26,6 +30,34 @@ const SENTENCES: TableDefinition<[u8; 32], SentenceDoc> = TableDefinition::new("
 const WORD_FREQ_INDEX: TableDefinition<(u64, [u8; 32]), ()> = TableDefinition::new("freq_index");
 const SENTENCE_RANK_INDEX: TableDefinition<(u64, [u8; 32]), ()> =
     TableDefinition::new("rankings_index");
+/// Inverted word index: for finding all the sentences that contain a specific word.
+/// Key is (word hash, sentence hash), the word hash being the same one `FrequencyDoc::hash()` produces. Value is empty for the same reason as in the frequency index.
+const WORD_SENTENCE_INDEX: TableDefinition<([u8; 32], [u8; 32]), ()> =
+    TableDefinition::new("word_sentence_index");
+
+/// Words the user has marked as known or ignored, in lowercase, by word hash (the same one `FrequencyDoc::hash()` produces). Words without a record here are unknown.
+const KNOWN_WORDS: TableDefinition<[u8; 32], KnownWordDoc> = TableDefinition::new("known_words");
+/// Hashes of the sentences already shown by the `random` command, so that the subsequent runs can only draw the ones that haven't been shown yet.
+const SEEN_SENTENCES: TableDefinition<[u8; 32], ()> = TableDefinition::new("seen_sentences");
+/// Batches of random sentences by their names, so that a batch can be refined over several runs.
+const BATCHES: TableDefinition<&str, BatchDoc> = TableDefinition::new("batches");
+
+/// Every file the database has been trained on, by a sequential source id.
+const SOURCES: TableDefinition<u64, SourceDoc> = TableDefinition::new("sources");
+/// The size and the modification time (nanoseconds since the Unix epoch) of every source that is a file, as it was when it was trained on, by source id. Lets an unchanged file be skipped without reading it again.
+const SOURCE_STAMPS: TableDefinition<u64, (u64, u64)> = TableDefinition::new("source_stamps");
+/// The last source id ever given out, so that the ids of untrained sources are never given to other files.
+const LAST_SOURCE_ID: TableDefinition<(), u64> = TableDefinition::new("last_source_id");
+/// Per-source word counts: how much every source contributed to the frequency of every word.
+/// Key is (source id, word hash), value is the count the source added to the word's frequency.
+const SOURCE_FREQS: TableDefinition<(u64, [u8; 32]), u64> = TableDefinition::new("source_freqs");
+/// Which sources every sentence appeared in.
+/// Key is (sentence hash, source id), value is the location of the sentence inside the source, for the source formats that have one (empty otherwise).
+const SENTENCE_SOURCES: TableDefinition<([u8; 32], u64), &str> =
+    TableDefinition::new("sentence_sources");
+
+/// Scratch table of the sentences waiting to be rescored, see `rescore_sentences()`. Filled and drained inside a single write transaction, so it's empty otherwise.
+const RESCORE_QUEUE: TableDefinition<[u8; 32], ()> = TableDefinition::new("rescore_queue");
 
 /// System config meta-table that for now only includes one record in the key, the current database version.
 const SYSTEM: TableDefinition<u32, ()> = TableDefinition::new("system_table");
@@ -63,24 +95,276 @@ impl FrequencyDoc {
     }
 }
 
+/// A word the user doesn't need to learn: either already known, or ignored (names, interjections etc.). Both count as "not unknown" when mining sentences.
+#[derive(Debug, Value)]
+pub struct KnownWordDoc {
+    pub word: String,
+    pub ignored: bool,
+}
+
+impl KnownWordDoc {
+    pub fn new(word: String, ignored: bool) -> Self {
+        Self { word, ignored }
+    }
+}
+
+/// A saved batch of random sentences: their hashes in the batch order, plus the settings they were drawn with, so that the regenerated sentences are drawn the same way.
+#[derive(Debug, Value)]
+pub struct BatchDoc {
+    pub hashes: Vec<[u8; 32]>,
+    pub min_easiness: Option<u64>,
+    pub unseen_only: bool,
+}
+
+impl BatchDoc {
+    pub fn new(hashes: Vec<[u8; 32]>, min_easiness: Option<u64>, unseen_only: bool) -> Self {
+        Self {
+            hashes,
+            min_easiness,
+            unseen_only,
+        }
+    }
+}
+
+/// A file the database has been trained on.
+#[derive(Debug, Value)]
+pub struct SourceDoc {
+    pub path: String,
+    /// blake3 hash of the file content.
+    pub content_hash: [u8; 32],
+    /// Unix timestamp (seconds) of the training.
+    pub trained_at: u64,
+    pub lang: String,
+    /// Number of unique words in the source.
+    pub word_count: u64,
+    pub sentence_count: u64,
+}
+
+impl SourceDoc {
+    /// A source that's about to be trained on. Its content hash and counts are filled in once it's been read through, see `TrainingRun::finish_source()`.
+    pub fn new(path: String, lang: String) -> Self {
+        Self {
+            path,
+            content_hash: [0u8; 32],
+            trained_at: Util::unix_now(),
+            lang,
+            word_count: 0,
+            sentence_count: 0,
+        }
+    }
+}
+
+/// Inclusive bounds on the values (frequencies or ratings) of an index scan. Unset bounds don't limit anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueBounds {
+    pub at_most: Option<u64>,
+    pub at_least: Option<u64>,
+}
+
+impl ValueBounds {
+    pub fn new(at_most: Option<u64>, at_least: Option<u64>) -> Self {
+        Self { at_most, at_least }
+    }
+
+    /// True if no value can possibly be inside these bounds.
+    pub fn is_empty(&self) -> bool {
+        matches!((self.at_most, self.at_least), (Some(most), Some(least)) if least > most)
+    }
+
+    /// The range of (inverted value, hash) index keys inside these bounds. Values are stored inverted in the indexes (so that the biggest ones come first), therefore `at_most` is where the range starts.
+    fn key_range(&self) -> RangeInclusive<(u64, [u8; 32])> {
+        (u64::MAX - self.at_most.unwrap_or(u64::MAX), [0u8; 32])
+            ..=(u64::MAX - self.at_least.unwrap_or(0), [u8::MAX; 32])
+    }
+}
+
 pub struct SageDatabase {
     db: Database,
     pub lang: String,
-    version_inconsistency: bool,
+    pub path: PathBuf,
+}
+
+/// A training run: all of its inputs are trained on in a single write transaction, so that either every one of them ends up in the database or none do. The stored sentences affected by the run are rescored once, when it's committed.
+pub struct TrainingRun {
+    wtx: WriteTransaction,
+    lang: String,
+    changed_words: HashSet<String>,
+}
+
+impl TrainingRun {
+    /// Finds a source with exactly the same content, to guard against training on the same text twice.
+    /// @param content_hash - blake3 hash of the content, see `HashingReader`.
+    /// @returns - the first source trained with this content (earlier in this run included), None if the content has never been trained on.
+    pub fn source_by_content(&self, content_hash: &[u8; 32]) -> Result<Option<(u64, SourceDoc)>> {
+        let source_table = self.wtx.open_table(SOURCES)?;
+        // NOTE: there are only as many sources as trained files, so a full scan is cheap enough to not need an index.
+        for row in source_table.iter()? {
+            let (id_guard, doc_guard) = row?;
+            let doc = doc_guard.value();
+            if &doc.content_hash == content_hash {
+                return Ok(Some((id_guard.value(), doc)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds a source trained from the same file, which hasn't changed since: it has the same path, size and modification time.
+    /// @param path - the path recorded for the source, see `Input::source_path()`.
+    /// @param stamp - the size and the modification time of the file, see `Input::stamp()`.
+    /// @returns - the last source trained from the unchanged file, None if there's none.
+    pub fn source_by_stamp(
+        &self,
+        path: &str,
+        stamp: (u64, u64),
+    ) -> Result<Option<(u64, SourceDoc)>> {
+        let source_table = self.wtx.open_table(SOURCES)?;
+        let stamp_table = self.wtx.open_table(SOURCE_STAMPS)?;
+        for row in source_table.iter()?.rev() {
+            let (id_guard, doc_guard) = row?;
+            let id = id_guard.value();
+            let doc = doc_guard.value();
+            if doc.path == path
+                && stamp_table
+                    .get(id)?
+                    .is_some_and(|guard| guard.value() == stamp)
+            {
+                return Ok(Some((id, doc)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Starts training on a new source. Its data is then trained on in batches with `train()`, and the source is finished with `finish_source()` (or taken back with `discard_source()`).
+    /// @param path - the path recorded for the source.
+    /// @returns - the id of the new source, one bigger than any id given out before (untrained sources included).
+    pub fn begin_source(&mut self, path: String) -> Result<u64> {
+        let mut source_table = self.wtx.open_table(SOURCES)?;
+        let mut last_id_table = self.wtx.open_table(LAST_SOURCE_ID)?;
+        // NOTE: the databases trained before the last id was kept only have their sources to go by.
+        let last_trained = source_table
+            .last()?
+            .map(|(id_guard, _)| id_guard.value())
+            .unwrap_or(0);
+        let last_given = last_id_table
+            .get(())?
+            .map(|guard| guard.value())
+            .unwrap_or(0);
+        let id = last_trained.max(last_given) + 1;
+        last_id_table.insert((), id)?;
+        source_table.insert(id, SourceDoc::new(path, self.lang.clone()))?;
+
+        Ok(id)
+    }
+
+    /// Trains on a batch of a source's data: its counts are added to the frequencies, its sentences are rated and inserted, and linked to the source.
+    /// @param id - the source the data comes from, see `begin_source()`.
+    pub fn train(&mut self, id: u64, data: &RawData) -> Result<()> {
+        SageDatabase::insert_freqs(&self.wtx, data)?;
+        SageDatabase::insert_rankings(&self.wtx, data, &self.lang)?;
+        SageDatabase::link_source(&self.wtx, id, data)?;
+        self.changed_words.extend(data.freqs.keys().cloned());
+
+        Ok(())
+    }
+
+    /// Records the content hash and the counts of a source once all of its data has been trained on.
+    /// @param sentence_count - the number of sentences read from the source.
+    /// @param stamp - the size and the modification time of the file the source was read from, None for the standard input.
+    /// @returns - the finished source doc.
+    pub fn finish_source(
+        &mut self,
+        id: u64,
+        content_hash: [u8; 32],
+        sentence_count: u64,
+        stamp: Option<(u64, u64)>,
+    ) -> Result<SourceDoc> {
+        let word_count = self
+            .wtx
+            .open_table(SOURCE_FREQS)?
+            .range((id, [0u8; 32])..=(id, [u8::MAX; 32]))?
+            .count() as u64;
+
+        let mut source_table = self.wtx.open_table(SOURCES)?;
+        let Some(mut source) = source_table.get(id)?.map(|guard| guard.value()) else {
+            bail!(
+                "TrainingRun::finish_source(): there's no source with id {} in this run.",
+                id
+            );
+        };
+        source.content_hash = content_hash;
+        source.word_count = word_count;
+        source.sentence_count = sentence_count;
+        source_table.insert(id, &source)?;
+        if let Some(stamp) = stamp {
+            self.wtx.open_table(SOURCE_STAMPS)?.insert(id, stamp)?;
+        }
+
+        Ok(source)
+    }
+
+    /// Takes back everything a source has contributed so far in this run, as if it had never been trained on, see `SageDatabase::untrain()`.
+    /// NOTE: the stored sentences it has rescored are rescored back right away, so that taking back a duplicate isn't counted as rescoring when the run is committed.
+    pub fn discard_source(&mut self, id: u64) -> Result<()> {
+        let (changed_words, _, _) = SageDatabase::remove_source(&self.wtx, id, &self.lang)?;
+        SageDatabase::rescore_sentences(&self.wtx, &changed_words, &self.lang)?;
+
+        Ok(())
+    }
+
+    /// Throws the whole run away, leaving the database as it was before it.
+    pub fn abort(self) -> Result<()> {
+        self.wtx.abort()?;
+
+        Ok(())
+    }
+
+    /// Rescores the stored sentences containing the words the run has changed, then commits the whole run.
+    /// @returns - the number of stored sentences whose rating has changed.
+    pub fn commit(self) -> Result<u64> {
+        let changed_words = self.changed_words.into_iter().collect::<Vec<String>>();
+        let rescored = SageDatabase::rescore_sentences(&self.wtx, &changed_words, &self.lang)?;
+        self.wtx.commit()?;
+
+        Ok(rescored)
+    }
 }
 
 impl SageDatabase {
-    pub fn new(lang: &str) -> Result<Self> {
-        // NOTE: Databases are called according to the language they're storing sentences/frequencies in, like English.redb, Finnish.redb etc.
-        let filename = format!("{}.redb", lang);
-        let filepath = std::path::Path::new(&filename);
+    /// Opens the database file.
+    /// @param filepath - the database file, see `default_path()` for where it's usually located.
+    /// @param lang - the language of the sentences/frequencies stored in the database.
+    /// @param create - if true, a database that doesn't exist yet is created (together with its directory). If false, a missing database is an error, so that a typo in the language or the path never silently creates a new empty database.
+    pub fn new(filepath: &Path, lang: &str, create: bool) -> Result<Self> {
         let db = if filepath.exists() {
             redb::Database::open(filepath)?
-        } else {
+        } else if create {
+            if let Some(dir) = filepath.parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).context(format!(
+                    "SageDatabase::new(): couldn't create the directory `{}` for the database.",
+                    dir.display()
+                ))?;
+            }
             redb::Database::create(filepath)?
+        } else {
+            let stray = PathBuf::from(format!("{}.redb", lang));
+            let hint = if stray.exists() && stray != filepath {
+                format!(
+                    " NOTE: `{}` exists in the current directory, move it to `{}` or use it with `--db` if that's the one you need.",
+                    stray.display(),
+                    filepath.display()
+                )
+            } else {
+                String::new()
+            };
+            bail!(
+                "SageDatabase::new(): the database `{}` for language `{}` doesn't exist. Run the command with `--create` to create it, or see the existing ones with the `list` command.{}",
+                filepath.display(),
+                lang,
+                hint
+            );
         };
-        let version_inconsistency: bool;
-
         {
             let wtx = db.begin_write()?;
 
@@ -88,21 +372,106 @@ impl SageDatabase {
             let _ = wtx.open_table(SENTENCES)?;
             let _ = wtx.open_table(WORD_FREQ_INDEX)?;
             let _ = wtx.open_table(SENTENCE_RANK_INDEX)?;
+            let _ = wtx.open_table(WORD_SENTENCE_INDEX)?;
+            let _ = wtx.open_table(KNOWN_WORDS)?;
+            let _ = wtx.open_table(SEEN_SENTENCES)?;
+            let _ = wtx.open_table(BATCHES)?;
+            let _ = wtx.open_table(SOURCES)?;
+            let _ = wtx.open_table(LAST_SOURCE_ID)?;
+            let _ = wtx.open_table(SOURCE_STAMPS)?;
+            let _ = wtx.open_table(SOURCE_FREQS)?;
+            let _ = wtx.open_table(SENTENCE_SOURCES)?;
 
             let _ = wtx.open_table(SYSTEM)?;
 
             wtx.commit()?;
 
-            version_inconsistency = Self::ensure_version_consistency(&db)?;
+            Self::ensure_version_consistency(&db, lang)?;
         }
 
         Ok(Self {
             db,
             lang: lang.to_owned(),
-            version_inconsistency,
+            path: filepath.to_owned(),
         })
     }
 
+    /// Looks up the frequencies of the words in an existing database without writing anything into it (not even the table creation `new()` does).
+    /// The words don't have to be in the database: the missing ones get a frequency of 0.
+    /// @param filepath - the database file to look into. Errors out if it doesn't exist yet.
+    /// @param words - the words to look up.
+    /// @returns - a map of every word to its frequency in the database.
+    pub fn peek_freqs(filepath: &Path, words: &[String]) -> Result<HashMap<String, u64>> {
+        let db = ReadOnlyDatabase::open(filepath).context(format!(
+            "SageDatabase::peek_freqs(): couldn't open `{}` for reading. Train the database for this language first.",
+            filepath.display()
+        ))?;
+        let rtx = db.begin_read()?;
+        let freqs = rtx.open_table(FREQUENCIES)?;
+        words
+            .iter()
+            .map(|w| {
+                let freq = freqs
+                    .get(&FrequencyDoc::new(w.clone(), 0).hash())?
+                    .map(|doc_guard| doc_guard.value().freq)
+                    .unwrap_or(0);
+                Ok((w.clone(), freq))
+            })
+            .collect::<Result<HashMap<_, _>>>()
+    }
+
+    /// The database file used for the language unless a specific file is requested.
+    /// NOTE: Databases are called according to the language they're storing sentences/frequencies in, like English.redb, Finnish.redb etc.
+    pub fn default_path(lang: &str) -> Result<PathBuf> {
+        Ok(Self::data_dir()?.join(format!("{}.redb", lang)))
+    }
+
+    /// The directory the language databases live in: the FREQSAGE_DATA_DIR env variable if it's set, otherwise the platform's data directory (`$XDG_DATA_HOME/freq-sage` or `~/.local/share/freq-sage` on Linux).
+    pub fn data_dir() -> Result<PathBuf> {
+        match std::env::var_os("FREQSAGE_DATA_DIR").filter(|d| !d.is_empty()) {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => dirs::data_dir()
+                .map(|d| d.join(DATA_DIR_NAME))
+                .context("SageDatabase::data_dir(): couldn't figure out the data directory of this platform. Set the FREQSAGE_DATA_DIR env variable (see .env.template)."),
+        }
+    }
+
+    /// Finds all the language databases in a directory.
+    /// @returns - pairs of (language, database file), sorted by language. Empty if the directory doesn't exist.
+    pub fn list_databases(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut found = vec![];
+        for entry in std::fs::read_dir(dir).context(format!(
+            "SageDatabase::list_databases(): couldn't read the data directory `{}`.",
+            dir.display()
+        ))? {
+            let path = entry?.path();
+            if path.is_file()
+                && path.extension().is_some_and(|e| e == "redb")
+                && let Some(lang) = path.file_stem().and_then(|s| s.to_str())
+            {
+                found.push((lang.to_owned(), path.clone()));
+            }
+        }
+        found.sort();
+
+        Ok(found)
+    }
+
+    /// Same as `status_check()`, but for a database that doesn't have to be opened for writing.
+    /// @returns - (frequency count, sentence count).
+    pub fn peek_status(filepath: &Path) -> Result<(u64, u64)> {
+        let db = ReadOnlyDatabase::open(filepath)?;
+        let rtx = db.begin_read()?;
+        let freqs = rtx.open_table(FREQUENCIES)?;
+        let sentences = rtx.open_table(SENTENCES)?;
+
+        Ok((freqs.len()?, sentences.len()?))
+    }
+
     pub fn status_check(status_db: &Self) -> Result<(u64, u64)> {
         let rtx = status_db.db.begin_read()?;
         let freqs = rtx.open_table(FREQUENCIES)?;
@@ -111,8 +480,23 @@ impl SageDatabase {
         Ok((freqs.len()?, sentences.len()?))
     }
 
-    pub fn insert_freqs(&mut self, data: &RawData) -> Result<()> {
-        let wtx = self.db.begin_write()?;
+    /// Begins a training run, see `TrainingRun`.
+    pub fn begin_training(&mut self) -> Result<TrainingRun> {
+        self.ensure_index_consistency(
+            WORD_SENTENCE_INDEX,
+            SENTENCES,
+            Self::inverted_index_inconsistent,
+            Self::build_word_sentence_index,
+        )?;
+
+        Ok(TrainingRun {
+            wtx: self.db.begin_write()?,
+            lang: self.lang.clone(),
+            changed_words: HashSet::new(),
+        })
+    }
+
+    fn insert_freqs(wtx: &WriteTransaction, data: &RawData) -> Result<()> {
         {
             let mut freq_table = wtx.open_table(FREQUENCIES)?;
             let mut index_table = wtx.open_table(WORD_FREQ_INDEX)?;
@@ -127,222 +511,1788 @@ impl SageDatabase {
                 freq_table.insert(&hash, doc)?;
 
                 if dbval != 0 {
-                    index_table.remove(&(dbval, hash))?;
+                    index_table.remove(&(u64::MAX - dbval, hash))?;
                 }
                 index_table.insert(&(u64::MAX - new_freq, hash), ())?;
             }
         }
-        wtx.commit()?;
         Ok(())
     }
 
-    pub fn insert_rankings(
-        &mut self,
-        db_freqs: HashMap<String, u64>,
-        sentences: Vec<String>,
-    ) -> Result<()> {
-        let data = RawData::from_preexisting_data(db_freqs, sentences, self.lang.clone());
+    /// Rates the sentences of the data against the frequencies stored in the database, which have to already include the data's own counts.
+    fn insert_rankings(wtx: &WriteTransaction, data: &RawData, lang: &str) -> Result<()> {
+        let db_freqs = {
+            let freq_table = wtx.open_table(FREQUENCIES)?;
+            let mut db_freqs = HashMap::new();
+            for word in data.freqs.keys() {
+                let freq = freq_table
+                    .get(&FrequencyDoc::new(word.clone(), 0).hash())?
+                    .map(|guard| guard.value().freq)
+                    .unwrap_or(0);
+                db_freqs.insert(word.clone(), freq);
+            }
+            db_freqs
+        };
+        let data =
+            RawData::from_preexisting_data(db_freqs, data.sentences.clone(), lang.to_owned());
         let ranker = SentenceRanker::new(&data);
         let new_rankings = ranker.rankings();
-        let wtx = self.db.begin_write()?;
+        let tokenizer = Tokenizer::new(lang);
         {
             let mut rank_table = wtx.open_table(SENTENCES)?;
             let mut index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+            let mut word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
 
             for ranking in new_rankings {
-                let mut doc = SentenceDoc::new(ranking.sentence.clone(), ranking.score);
                 let hash = Util::hash_words(&ranking.words);
-                let dbval = rank_table
-                    .get_mut(hash)?
-                    .map(|guard| guard.value().rating)
-                    .unwrap_or(0);
-                let new_val = if dbval == 0 {
-                    doc.rating
-                } else {
-                    SentenceRanker::rank_sentence(&doc.raw, &data.freqs, None).expect("SageDatabase::insert_rankings(): sentence rank is supposed to always be Some here, but it's None?").score
+                // NOTE: a sentence that's already stored keeps its text, so that which of its spellings ends up stored doesn't depend on how the input was split into batches.
+                let doc = match rank_table.get(hash)?.map(|guard| guard.value()) {
+                    Some(mut doc) => {
+                        index_table.remove(&(u64::MAX - doc.rating, hash))?;
+                        doc.rating = SentenceRanker::rank_sentence(&doc.raw, &tokenizer, &data.freqs, None).expect("SageDatabase::insert_rankings(): sentence rank is supposed to always be Some here, but it's None?").score;
+                        doc
+                    }
+                    None => {
+                        for word_hash in Self::unique_word_hashes(&ranking.words) {
+                            word_index_table.insert(&(word_hash, hash), ())?;
+                        }
+                        SentenceDoc::new(ranking.sentence.clone(), ranking.score)
+                    }
                 };
-                doc.rating = new_val;
+                index_table.insert(&(u64::MAX - doc.rating, hash), ())?;
                 rank_table.insert(hash, doc)?;
-
-                if dbval != 0 {
-                    index_table.remove(&(dbval, hash))?;
-                }
-                index_table.insert(&(u64::MAX - new_val, hash), ())?;
             }
         }
-        wtx.commit()?;
 
         Ok(())
     }
 
-    pub fn freqs_of_words(&self, words: &[String]) -> Result<HashMap<String, u64>> {
-        let rtx = self.db.begin_read()?;
-        let freqs = rtx.open_table(FREQUENCIES)?;
-        words.iter().map(|w|{
-            let doc = FrequencyDoc::new(w.clone(), 0);
-            match freqs.get(&doc.hash())? {
-                Some(doc_guard) => Ok((doc.word, doc_guard.value().freq)),
-                None => panic!("{}", &format!("UNREACHABLE: only words whose records already exist should be looked up in this method, and yet the word `{}` did not exist...", w)),
+    /// Links a batch of a source's data to it: how much it contributed to every word frequency, and which of the stored sentences appeared in it. Has to be called after the sentences of the batch are inserted, because sentences discarded from the rankings aren't linked.
+    /// @param id - the source the data comes from.
+    /// @param data - a batch of the data analyzed from the source.
+    fn link_source(wtx: &WriteTransaction, id: u64, data: &RawData) -> Result<()> {
+        let mut source_freq_table = wtx.open_table(SOURCE_FREQS)?;
+        let mut sentence_source_table = wtx.open_table(SENTENCE_SOURCES)?;
+        let sentence_table = wtx.open_table(SENTENCES)?;
+
+        for (word, freq) in &data.freqs {
+            let hash = FrequencyDoc::new(word.clone(), 0).hash();
+            let previous = source_freq_table
+                .get((id, hash))?
+                .map(|guard| guard.value())
+                .unwrap_or(0);
+            source_freq_table.insert((id, hash), previous.saturating_add(*freq))?;
+        }
+
+        let tokenizer = Tokenizer::new(&data.lang);
+        for (i, sentence) in data.sentences.iter().enumerate() {
+            let hash = Util::hash_words(&tokenizer.words(sentence));
+            // NOTE: a sentence repeated in the source keeps the location it first appeared at.
+            if sentence_table.get(&hash)?.is_some()
+                && sentence_source_table.get((hash, id))?.is_none()
+            {
+                let location = data.locations.get(i).map_or("", String::as_str);
+                sentence_source_table.insert((hash, id), location)?;
             }
-        }).collect::<Result<HashMap<_,_>, _>>()
-    }
+        }
 
-    // REFACTOR: [???] can top_freqs() and top_rankings() be merged into a single function?
-    pub fn top_freqs(&mut self, maybe_limit: Option<u32>) -> Result<Vec<FrequencyDoc>> {
-        self.ensure_index_consistency(WORD_FREQ_INDEX, FREQUENCIES, Self::build_freq_index)?;
+        Ok(())
+    }
 
+    /// All the files the database has been trained on.
+    /// @returns - pairs of (source id, source doc), in the order they were trained.
+    pub fn sources(&self) -> Result<Vec<(u64, SourceDoc)>> {
         let rtx = self.db.begin_read()?;
-        let index_table = rtx.open_table(WORD_FREQ_INDEX)?;
-        let primary_freqs_table = rtx.open_table(FREQUENCIES)?;
+        let source_table = rtx.open_table(SOURCES)?;
+        source_table
+            .iter()?
+            .map(|row| {
+                let (id_guard, doc_guard) = row?;
+                Ok((id_guard.value(), doc_guard.value()))
+            })
+            .collect()
+    }
 
-        let limit = maybe_limit.map(|l| l as usize).unwrap_or(usize::MAX);
-        let mut result = Vec::with_capacity(maybe_limit.unwrap_or(0) as usize);
+    /// Removes everything a source has contributed to the database, as if the file had never been trained on. Everything is done in a single write transaction, so the rankings are never left stale:
+    /// - its word counts are subtracted from the frequencies, words that drop to 0 are removed completely;
+    /// - the sentences that appeared in no other source are removed, together with their index records;
+    /// - the source itself is forgotten;
+    /// - the remaining sentences containing the changed words are rescored.
+    ///   @param id - the id of the source to remove.
+    ///   @returns - a tuple of (words whose frequencies changed but are still in the database, number of removed words, number of removed sentences, number of rescored sentences).
+    pub fn untrain(&mut self, id: u64) -> Result<(Vec<String>, u64, u64, u64)> {
+        self.ensure_index_consistency(
+            WORD_SENTENCE_INDEX,
+            SENTENCES,
+            Self::inverted_index_inconsistent,
+            Self::build_word_sentence_index,
+        )?;
 
-        for row in index_table.iter()?.take(limit) {
-            let (key_guard, _) = row?;
-            let (_, hash) = key_guard.value();
-            let (real_freq, word) = primary_freqs_table
-                .get(&hash)?
-                .map(|doc_guard| (doc_guard.value().freq, doc_guard.value().word))
-                .context("SageDatabase::top_freqs(): UNREACHABLE: hash from index table is not in the primary frequencies table???")?;
-            result.push(FrequencyDoc::new(word, real_freq));
-        }
+        let wtx = self.db.begin_write()?;
+        let (changed_words, removed_words, removed_sentences) =
+            Self::remove_source(&wtx, id, &self.lang)?;
+        let rescored = Self::rescore_sentences(&wtx, &changed_words, &self.lang)?;
+        wtx.commit()?;
 
-        Ok(result)
+        Ok((changed_words, removed_words, removed_sentences, rescored))
     }
 
-    pub fn top_rankings(&mut self, maybe_limit: Option<u32>) -> Result<Vec<SentenceDoc>> {
-        self.ensure_index_consistency(SENTENCE_RANK_INDEX, SENTENCES, Self::build_rankings_index)?;
+    /// The removal part of `untrain()`, inside an already open write transaction. The sentences containing the changed words are left for the caller to rescore.
+    fn remove_source(
+        wtx: &WriteTransaction,
+        id: u64,
+        lang: &str,
+    ) -> Result<(Vec<String>, u64, u64)> {
+        let tokenizer = Tokenizer::new(lang);
+        let mut changed_words = vec![];
+        let mut removed_words: u64 = 0;
+        let mut removed_sentences: u64 = 0;
+        {
+            let mut source_table = wtx.open_table(SOURCES)?;
+            let mut stamp_table = wtx.open_table(SOURCE_STAMPS)?;
+            let mut source_freq_table = wtx.open_table(SOURCE_FREQS)?;
+            let mut sentence_source_table = wtx.open_table(SENTENCE_SOURCES)?;
+            let mut freq_table = wtx.open_table(FREQUENCIES)?;
+            let mut freq_index_table = wtx.open_table(WORD_FREQ_INDEX)?;
+            let mut sentence_table = wtx.open_table(SENTENCES)?;
+            let mut rank_index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+            let mut word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+            let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
 
-        let rtx = self.db.begin_read()?;
-        let index_table = rtx.open_table(SENTENCE_RANK_INDEX)?;
-        let primary_table = rtx.open_table(SENTENCES)?;
+            if source_table.remove(id)?.is_none() {
+                bail!(
+                    "SageDatabase::remove_source(): there's no source with id {}, see the `sources` command for the trained sources.",
+                    id
+                );
+            }
+            stamp_table.remove(id)?;
 
-        let limit = maybe_limit.map(|l| l as usize).unwrap_or(usize::MAX);
-        let mut result = Vec::with_capacity(maybe_limit.unwrap_or(0) as usize);
+            let contributions = source_freq_table
+                .range((id, [0u8; 32])..=(id, [u8::MAX; 32]))?
+                .map(|row| {
+                    row.map(|(key_guard, count_guard)| (key_guard.value().1, count_guard.value()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for (word_hash, count) in contributions {
+                source_freq_table.remove((id, word_hash))?;
+                let Some(mut doc) = freq_table.get(&word_hash)?.map(|guard| guard.value()) else {
+                    continue;
+                };
 
-        for row in index_table.iter()?.take(limit) {
-            let (key_guard, _) = row?;
-            let (_, hash) = key_guard.value();
-            let (real_rank, sentence) = primary_table.get(&hash)?.map(|guard| (guard.value().rating, guard.value().raw)).context("SageDatabase::top_rankings(): UNREACHABLE: hash from index table is not in the primary sentences table???")?;
-            result.push(SentenceDoc::new(sentence, real_rank));
-        }
+                freq_index_table.remove(&(u64::MAX - doc.freq, word_hash))?;
+                doc.freq = doc.freq.saturating_sub(count);
+                if doc.freq == 0 {
+                    freq_table.remove(&word_hash)?;
+                    removed_words += 1;
+                } else {
+                    freq_index_table.insert(&(u64::MAX - doc.freq, word_hash), ())?;
+                    changed_words.push(doc.word.clone());
+                    freq_table.insert(&word_hash, doc)?;
+                }
+            }
 
-        Ok(result)
-    }
+            // NOTE: sentence links are keyed by the sentence hash first, so finding the sentences of one source takes a full scan. Untraining is rare enough for that to be fine.
+            let linked: Vec<[u8; 32]> = sentence_source_table
+                .iter()?
+                .filter_map(|row| match row {
+                    Ok((key_guard, _)) => {
+                        let (hash, source_id) = key_guard.value();
+                        (source_id == id).then_some(Ok(hash))
+                    }
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for hash in linked {
+                sentence_source_table.remove((hash, id))?;
+                if sentence_source_table
+                    .range((hash, 0)..=(hash, u64::MAX))?
+                    .next()
+                    .is_some()
+                {
+                    continue;
+                }
 
-    fn version(db: &Database) -> Result<u32> {
-        let rtx = db.begin_read()?;
-        let systb = rtx.open_table(SYSTEM)?;
-        if systb.len()? > 1 {
-            panic!(
-                "SageDatabase::ensure_version_consistency(): SYSTEM table has more than one record, but is supposed to only have one, where all the config variables are stored. This may mean that the data in the DB is inconsistent, or something went horribly wrong."
-            );
+                let Some(doc) = sentence_table.remove(&hash)?.map(|guard| guard.value()) else {
+                    continue;
+                };
+                Self::remove_sentence_records(
+                    &mut rank_index_table,
+                    &mut word_index_table,
+                    &mut seen_table,
+                    &tokenizer,
+                    hash,
+                    &doc,
+                )?;
+                removed_sentences += 1;
+            }
         }
-        Ok(systb
-            .first()?
-            .map(|(ver_guard, _)| ver_guard.value())
-            .unwrap_or(0))
+
+        Ok((changed_words, removed_words, removed_sentences))
     }
 
-    /// Static method that compares the version constant in the code and the version number in the database.
-    /// @param db - the database to perform the check on.
-    /// @returns - a Result<bool>. The result part is a possible IO error while reading from the DB. The bool part: true if a version inconsistency has been SPOTTED, false if the version is consistent between the code and the DB.
-    fn ensure_version_consistency(db: &Database) -> Result<bool> {
-        let current_version = Self::version(db)?;
+    /// Finds the sentences (and optionally the words) matching a regex, and removes them from the database together with all their index records and source links. Everything is done in a single write transaction.
+    /// The word counts of the removed sentences stay in the frequencies. The sentences containing the removed words stay too, and are rescored without them.
+    /// @param pattern - the regex to match the raw sentences (and the words) against.
+    /// @param words_too - if true, the words matching the regex are removed as well.
+    /// @param dry_run - if true, only finds what would be removed without removing anything.
+    /// @returns - a tuple of (removed or matching sentences, removed or matching words, number of rescored sentences).
+    pub fn clean(
+        &mut self,
+        pattern: &Regex,
+        words_too: bool,
+        dry_run: bool,
+    ) -> Result<(Vec<String>, Vec<String>, u64)> {
+        let mut sentences: Vec<([u8; 32], SentenceDoc)> = vec![];
+        let mut words: Vec<([u8; 32], FrequencyDoc)> = vec![];
+        {
+            let rtx = self.db.begin_read()?;
+            let sentence_table = rtx.open_table(SENTENCES)?;
+            for row in sentence_table.iter()? {
+                let (hash_guard, doc_guard) = row?;
+                let doc = doc_guard.value();
+                if pattern.is_match(&doc.raw) {
+                    sentences.push((hash_guard.value(), doc));
+                }
+            }
 
-        match current_version.cmp(&(REDB_LAYOUT_VERSION).into()) {
-            // NOTE: if we've extracted a certain version from the DB, and it's exactly equal to the current DB layout version in our code, that means the version is up-to-date and we can safely return and do nothing.
-            Ordering::Equal => Ok(false),
-            Ordering::Less => {
-                let wtx = db.begin_write()?;
-                {
-                    let mut systb = wtx.open_table(SYSTEM)?;
-                    if current_version != 0 {
-                        systb.remove(current_version)?;
+            if words_too {
+                let freq_table = rtx.open_table(FREQUENCIES)?;
+                for row in freq_table.iter()? {
+                    let (hash_guard, doc_guard) = row?;
+                    let doc = doc_guard.value();
+                    if pattern.is_match(&doc.word) {
+                        words.push((hash_guard.value(), doc));
                     }
-                    systb.insert(REDB_LAYOUT_VERSION as u32, ())?;
                 }
-                wtx.commit()?;
-                Ok(true)
             }
-            Ordering::Greater => panic!(
-                "SageDatabase::ensure_version_consistency(): the DB version in the code is smaller than the version in the database itself. This means an inconsistency between the code and the DB, this needs to be investigated separately - it shouldn't happen if FreQ Sage is behaving properly."
-            ),
         }
-    }
 
-    /// Rebuilds an index for a primary table if one of these conditions is true:
-    /// - Index table doesn't exist;
-    /// - DB init noticed a version inconsistency between code and DB;
-    /// - Index table and primary table lengths are not equal.
-    ///   @returns - Ok(()) if all went well, Err(_) if IO errors.
-    fn ensure_index_consistency<KI, VI, KP, VP, F>(
-        &mut self,
-        index_table_def: TableDefinition<KI, VI>,
-        primary_table_def: TableDefinition<KP, VP>,
-        index_build_func: F,
-    ) -> Result<()>
-    where
-        KI: redb::Key,
-        KP: redb::Key,
-        VI: redb::Value,
-        VP: redb::Value,
-        F: FnOnce(&mut Self) -> Result<()>,
-    {
-        let rtx = self.db.begin_read()?;
-        let need_rebuild = match rtx.open_table(index_table_def) {
-            Ok(index_table) => {
-                if self.version_inconsistency {
-                    true
-                } else {
-                    let freq_table = rtx.open_table(primary_table_def)?;
-                    index_table.len()? != freq_table.len()?
+        let mut rescored: u64 = 0;
+        if !dry_run {
+            self.ensure_index_consistency(
+                WORD_SENTENCE_INDEX,
+                SENTENCES,
+                Self::inverted_index_inconsistent,
+                Self::build_word_sentence_index,
+            )?;
+
+            let tokenizer = Tokenizer::new(&self.lang);
+            let wtx = self.db.begin_write()?;
+            {
+                let mut sentence_table = wtx.open_table(SENTENCES)?;
+                let mut rank_index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+                let mut word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+                let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
+                let mut sentence_source_table = wtx.open_table(SENTENCE_SOURCES)?;
+                let mut freq_table = wtx.open_table(FREQUENCIES)?;
+                let mut freq_index_table = wtx.open_table(WORD_FREQ_INDEX)?;
+                let mut source_freq_table = wtx.open_table(SOURCE_FREQS)?;
+
+                for (hash, doc) in &sentences {
+                    sentence_table.remove(hash)?;
+                    Self::remove_sentence_records(
+                        &mut rank_index_table,
+                        &mut word_index_table,
+                        &mut seen_table,
+                        &tokenizer,
+                        *hash,
+                        doc,
+                    )?;
+                    sentence_source_table
+                        .retain_in((*hash, 0)..=(*hash, u64::MAX), |_, _| false)?;
+                }
+
+                let removed_hashes: HashSet<[u8; 32]> = words.iter().map(|(h, _)| *h).collect();
+                for (hash, doc) in &words {
+                    freq_table.remove(hash)?;
+                    freq_index_table.remove(&(u64::MAX - doc.freq, *hash))?;
+                }
+                if !removed_hashes.is_empty() {
+                    // NOTE: source word counts are keyed by the source id first, so they're found with a full scan.
+                    source_freq_table
+                        .retain(|(_, word_hash), _| !removed_hashes.contains(&word_hash))?;
                 }
             }
-            Err(_) => true,
-        };
-        if need_rebuild {
-            index_build_func(self)
-        } else {
-            Ok(())
+
+            // NOTE: the sentences containing the removed words are found through the inverted index, so its records of the words are only removed after the rescore.
+            let removed_words: Vec<String> =
+                words.iter().map(|(_, doc)| doc.word.clone()).collect();
+            rescored = Self::rescore_sentences(&wtx, &removed_words, &self.lang)?;
+            {
+                let mut word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+                for (hash, _) in &words {
+                    word_index_table
+                        .retain_in((*hash, [0u8; 32])..=(*hash, [u8::MAX; 32]), |_, _| false)?;
+                }
+            }
+            wtx.commit()?;
         }
+
+        Ok((
+            sentences.into_iter().map(|(_, doc)| doc.raw).collect(),
+            words.into_iter().map(|(_, doc)| doc.word).collect(),
+            rescored,
+        ))
     }
 
-    // REFACTOR: [???] can build_freq_index() and build_rankings_index() be merged into a single function?
-    fn build_freq_index(&mut self) -> Result<()> {
-        let wtx = self.db.begin_write()?;
-        {
-            let primary_table = wtx.open_table(FREQUENCIES)?;
-            wtx.delete_table(WORD_FREQ_INDEX)?;
-            let mut index_table = wtx.open_table(WORD_FREQ_INDEX)?;
-            for primary_pair in primary_table.iter()? {
-                let (hash_guard, freq_doc_guard) = primary_pair?;
-                let hash = hash_guard.value();
-                let doc_freq = freq_doc_guard.value().freq;
-                index_table.insert((u64::MAX - doc_freq, hash), ())?;
-            }
+    /// The sources a stored sentence appeared in.
+    /// @param raw - the sentence as it's stored in the database.
+    /// @returns - triples of (source id, source doc, location of the sentence in the source, empty if unknown).
+    pub fn sources_of_sentence(&self, raw: &str) -> Result<Vec<(u64, SourceDoc, String)>> {
+        let rtx = self.db.begin_read()?;
+        let source_table = rtx.open_table(SOURCES)?;
+        let sentence_source_table = rtx.open_table(SENTENCE_SOURCES)?;
+
+        let hash = Util::hash_words(&Tokenizer::new(&self.lang).words(raw));
+        let mut result = vec![];
+        for row in sentence_source_table.range((hash, 0)..=(hash, u64::MAX))? {
+            let (key_guard, location_guard) = row?;
+            let (_, id) = key_guard.value();
+            let source = source_table.get(id)?.map(|guard| guard.value()).context("SageDatabase::sources_of_sentence(): UNREACHABLE: source id from the sentence sources table is not in the sources table???")?;
+            result.push((id, source, location_guard.value().to_owned()));
         }
-        wtx.commit()?;
 
-        Ok(())
+        Ok(result)
     }
 
-    fn build_rankings_index(&mut self) -> Result<()> {
-        let wtx = self.db.begin_write()?;
+    /// Rescores the stored sentences containing any of the changed words against the current frequencies. Training, untraining and cleaning change the frequencies of words that older sentences contain as well, so without this pass their ratings (and the order of the rankings index) go stale. The affected sentences are queued in `RESCORE_QUEUE` first, so that a sentence containing several of the words is only rescored once, and a run changing most of the vocabulary doesn't have to hold every sentence hash in memory at once.
+    /// @returns - the number of sentences whose rating has changed.
+    fn rescore_sentences(
+        wtx: &WriteTransaction,
+        changed_words: &[String],
+        lang: &str,
+    ) -> Result<u64> {
+        let tokenizer = Tokenizer::new(lang);
+        let mut rescored: u64 = 0;
         {
-            let primary_table = wtx.open_table(SENTENCES)?;
-            wtx.delete_table(SENTENCE_RANK_INDEX)?;
+            let freq_table = wtx.open_table(FREQUENCIES)?;
+            let mut sentence_table = wtx.open_table(SENTENCES)?;
             let mut index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
-            for primary_pair in primary_table.iter()? {
-                let (hash_guard, sentence_doc_guard) = primary_pair?;
-                let hash = hash_guard.value();
-                let ranking = sentence_doc_guard.value().rating;
-                index_table.insert((u64::MAX - ranking, hash), ())?;
+            let word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+            let mut queue_table = wtx.open_table(RESCORE_QUEUE)?;
+
+            for word_hash in Self::unique_word_hashes(changed_words) {
+                for row in
+                    word_index_table.range((word_hash, [0u8; 32])..=(word_hash, [u8::MAX; 32]))?
+                {
+                    let (key_guard, _) = row?;
+                    queue_table.insert(key_guard.value().1, ())?;
+                }
             }
-        }
-        wtx.commit()?;
 
-        Ok(())
+            // NOTE: the same words show up in a lot of sentences, so their frequencies are only looked up in the primary table once.
+            let mut freq_cache: HashMap<String, u64> = HashMap::new();
+            while let Some((hash_guard, _)) = queue_table.pop_first()? {
+                let hash = hash_guard.value();
+                let Some(mut doc) = sentence_table.get(&hash)?.map(|guard| guard.value()) else {
+                    continue;
+                };
+                let words = tokenizer.words(&doc.raw);
+                Self::cache_freqs(&freq_table, &words, &mut freq_cache)?;
+
+                let new_rating = SentenceRanker::score_words(&words, &freq_cache);
+                if new_rating == doc.rating {
+                    continue;
+                }
+
+                index_table.remove(&(u64::MAX - doc.rating, hash))?;
+                index_table.insert(&(u64::MAX - new_rating, hash), ())?;
+                doc.rating = new_rating;
+                sentence_table.insert(hash, doc)?;
+                rescored += 1;
+            }
+        }
+
+        Ok(rescored)
+    }
+
+    /// Finds all the stored sentences that contain the word, through the inverted word index.
+    /// @param word - the word to look for, has to already be cleaned up the same way `Tokenizer::words()` does it.
+    /// @param maybe_limit - if Some, only this many of the easiest sentences are returned.
+    /// @returns - the sentences containing the word, sorted from the easiest to the hardest.
+    pub fn sentences_with_word(
+        &mut self,
+        word: &str,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<SentenceDoc>> {
+        self.ensure_index_consistency(
+            WORD_SENTENCE_INDEX,
+            SENTENCES,
+            Self::inverted_index_inconsistent,
+            Self::build_word_sentence_index,
+        )?;
+
+        let rtx = self.db.begin_read()?;
+        let word_index_table = rtx.open_table(WORD_SENTENCE_INDEX)?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+
+        let word_hash = FrequencyDoc::new(word.to_owned(), 0).hash();
+        let mut result = vec![];
+        for hash in Self::sentence_hashes_with_word(&word_index_table, word_hash)? {
+            let doc = primary_table.get(&hash)?.map(|guard| guard.value()).context("SageDatabase::sentences_with_word(): UNREACHABLE: hash from the word index table is not in the primary sentences table???")?;
+            result.push(doc);
+        }
+
+        Ok(Self::easiest_first(result, maybe_limit))
+    }
+
+    /// Finds all the stored sentences that contain the exact sequence of words. Candidates are the sentences that contain every word of the phrase according to the inverted word index, which are then checked for the words being consecutive.
+    /// @param phrase - the words of the phrase, cleaned up the same way `Tokenizer::words()` does it.
+    /// @param maybe_limit - if Some, only this many of the easiest sentences are returned.
+    /// @returns - the sentences containing the phrase, sorted from the easiest to the hardest.
+    pub fn sentences_with_phrase(
+        &mut self,
+        phrase: &[String],
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<SentenceDoc>> {
+        if phrase.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.ensure_index_consistency(
+            WORD_SENTENCE_INDEX,
+            SENTENCES,
+            Self::inverted_index_inconsistent,
+            Self::build_word_sentence_index,
+        )?;
+
+        let rtx = self.db.begin_read()?;
+        let word_index_table = rtx.open_table(WORD_SENTENCE_INDEX)?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+
+        let mut candidates: Option<HashSet<[u8; 32]>> = None;
+        for word_hash in Self::unique_word_hashes(phrase) {
+            let with_word = Self::sentence_hashes_with_word(&word_index_table, word_hash)?;
+            candidates = Some(match candidates {
+                None => with_word,
+                Some(c) => c.intersection(&with_word).copied().collect(),
+            });
+        }
+
+        let tokenizer = Tokenizer::new(&self.lang);
+        let mut result = vec![];
+        for hash in candidates.unwrap_or_default() {
+            let doc = primary_table.get(&hash)?.map(|guard| guard.value()).context("SageDatabase::sentences_with_phrase(): UNREACHABLE: hash from the word index table is not in the primary sentences table???")?;
+            if tokenizer
+                .words(&doc.raw)
+                .windows(phrase.len())
+                .any(|w| w == phrase)
+            {
+                result.push(doc);
+            }
+        }
+
+        Ok(Self::easiest_first(result, maybe_limit))
+    }
+
+    /// Finds all the stored sentences whose raw text matches the regex. There's no index to help here, so every sentence in the database is checked.
+    /// @param pattern - the regex to match the raw sentences against.
+    /// @param maybe_limit - if Some, only this many of the easiest sentences are returned.
+    /// @returns - the matching sentences, sorted from the easiest to the hardest.
+    pub fn sentences_matching(
+        &self,
+        pattern: &Regex,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<SentenceDoc>> {
+        let rtx = self.db.begin_read()?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+
+        let mut result = vec![];
+        for row in primary_table.iter()? {
+            let (_, doc_guard) = row?;
+            let doc = doc_guard.value();
+            if pattern.is_match(&doc.raw) {
+                result.push(doc);
+            }
+        }
+
+        Ok(Self::easiest_first(result, maybe_limit))
+    }
+
+    /// Pages through the word frequency index, from the most frequent word to the least frequent one.
+    /// @param bounds - only the words with frequencies inside these bounds are shown. Found through a range scan on the index.
+    /// @param offset - how many words to skip from the start of the bounds.
+    /// @param maybe_limit - if Some, only this many words are returned.
+    /// @returns - pairs of (real rank of the word in the whole index, starting from 1; the word's frequency doc).
+    pub fn top_freqs(
+        &mut self,
+        bounds: ValueBounds,
+        offset: u64,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<(u64, FrequencyDoc)>> {
+        self.ensure_index_consistency(
+            WORD_FREQ_INDEX,
+            FREQUENCIES,
+            Self::index_inconsistent,
+            Self::build_freq_index,
+        )?;
+
+        let rtx = self.db.begin_read()?;
+        let index_table = rtx.open_table(WORD_FREQ_INDEX)?;
+        let primary_freqs_table = rtx.open_table(FREQUENCIES)?;
+
+        let mut result = Vec::with_capacity(maybe_limit.unwrap_or(0) as usize);
+        for (rank, hash) in Self::index_page(&index_table, bounds, offset, maybe_limit)? {
+            let doc = primary_freqs_table
+                .get(&hash)?
+                .map(|doc_guard| doc_guard.value())
+                .context("SageDatabase::top_freqs(): UNREACHABLE: hash from index table is not in the primary frequencies table???")?;
+            result.push((rank, doc));
+        }
+
+        Ok(result)
+    }
+
+    /// Pages through the sentence rankings index, from the easiest sentence to the hardest one.
+    /// @param bounds - only the sentences with ratings inside these bounds are shown. Found through a range scan on the index.
+    /// @param offset - how many sentences to skip from the start of the bounds.
+    /// @param maybe_limit - if Some, only this many sentences are returned.
+    /// @returns - pairs of (real rank of the sentence in the whole index, starting from 1; the sentence doc).
+    pub fn top_rankings(
+        &mut self,
+        bounds: ValueBounds,
+        offset: u64,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<(u64, SentenceDoc)>> {
+        self.ensure_index_consistency(
+            SENTENCE_RANK_INDEX,
+            SENTENCES,
+            Self::index_inconsistent,
+            Self::build_rankings_index,
+        )?;
+
+        let rtx = self.db.begin_read()?;
+        let index_table = rtx.open_table(SENTENCE_RANK_INDEX)?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+
+        let mut result = Vec::with_capacity(maybe_limit.unwrap_or(0) as usize);
+        for (rank, hash) in Self::index_page(&index_table, bounds, offset, maybe_limit)? {
+            let doc = primary_table.get(&hash)?.map(|guard| guard.value()).context("SageDatabase::top_rankings(): UNREACHABLE: hash from index table is not in the primary sentences table???")?;
+            result.push((rank, doc));
+        }
+
+        Ok(result)
+    }
+
+    /// Marks the words as known (or ignored), overwriting whatever status they had before. Words are marked case-insensitively: marking `the` marks `The` at the start of a sentence too.
+    /// @param words - the words to mark, cleaned up the same way `Tokenizer::words()` does it.
+    /// @param ignored - true if the words should be ignored rather than known.
+    /// @returns - the number of words marked.
+    pub fn mark_known(&mut self, words: &[String], ignored: bool) -> Result<u64> {
+        let wtx = self.db.begin_write()?;
+        let mut marked: u64 = 0;
+        {
+            let mut known_table = wtx.open_table(KNOWN_WORDS)?;
+            for word in words {
+                let word = word.to_lowercase();
+                let hash = FrequencyDoc::new(word.clone(), 0).hash();
+                known_table.insert(hash, KnownWordDoc::new(word, ignored))?;
+                marked += 1;
+            }
+        }
+        wtx.commit()?;
+
+        Ok(marked)
+    }
+
+    /// Marks the words as unknown again by removing their known/ignored records, case-insensitively.
+    /// @returns - the number of records removed.
+    pub fn mark_unknown(&mut self, words: &[String]) -> Result<u64> {
+        let words: HashSet<String> = words.iter().map(|w| w.to_lowercase()).collect();
+        let wtx = self.db.begin_write()?;
+        let marked;
+        {
+            let mut known_table = wtx.open_table(KNOWN_WORDS)?;
+            // NOTE: a full scan, as the records marked before words were lowercased may be in any case. There are only as many records as words the user has marked.
+            let before = known_table.len()?;
+            known_table.retain(|_, doc| !words.contains(&doc.word.to_lowercase()))?;
+            marked = before - known_table.len()?;
+        }
+        wtx.commit()?;
+
+        Ok(marked)
+    }
+
+    /// Finds the "i+1" sentences: the ones that contain exactly one unknown word (neither known nor ignored, in any case). Walks the rankings index from the easiest sentence, so the result is already ranked.
+    /// @param bounds - only the sentences with ratings inside these bounds are considered.
+    /// @param offset - how many i+1 sentences to skip.
+    /// @param maybe_limit - if Some, stops after finding this many sentences.
+    /// @returns - pairs of (sentence, its only unknown word as it's first written in the sentence), from the easiest to the hardest.
+    pub fn i_plus_one(
+        &mut self,
+        bounds: ValueBounds,
+        offset: u64,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<(SentenceDoc, String)>> {
+        self.ensure_index_consistency(
+            SENTENCE_RANK_INDEX,
+            SENTENCES,
+            Self::index_inconsistent,
+            Self::build_rankings_index,
+        )?;
+
+        let rtx = self.db.begin_read()?;
+        let index_table = rtx.open_table(SENTENCE_RANK_INDEX)?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+        let known_table = rtx.open_table(KNOWN_WORDS)?;
+
+        let known = known_table
+            .iter()?
+            .map(|row| row.map(|(_, doc_guard)| doc_guard.value().word.to_lowercase()))
+            .collect::<Result<HashSet<String>, _>>()?;
+
+        if bounds.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let limit = maybe_limit.map(|l| l as usize).unwrap_or(usize::MAX);
+        let tokenizer = Tokenizer::new(&self.lang);
+        let mut to_skip = offset;
+        let mut result = vec![];
+        for row in index_table.range(bounds.key_range())? {
+            if result.len() >= limit {
+                break;
+            }
+            let (key_guard, _) = row?;
+            let (_, hash) = key_guard.value();
+            let doc = primary_table.get(&hash)?.map(|guard| guard.value()).context("SageDatabase::i_plus_one(): UNREACHABLE: hash from index table is not in the primary sentences table???")?;
+
+            // NOTE: the unknown words by their lowercase form, so that `The` and `the` are the same word.
+            let mut unknown: HashMap<String, String> = HashMap::new();
+            for word in tokenizer.words(&doc.raw) {
+                let folded = word.to_lowercase();
+                if !known.contains(&folded) {
+                    unknown.entry(folded).or_insert(word);
+                }
+            }
+            if unknown.len() == 1 {
+                if to_skip > 0 {
+                    to_skip -= 1;
+                    continue;
+                }
+                let word = unknown.into_values().next().expect(
+                    "SageDatabase::i_plus_one(): UNREACHABLE: a map of length 1 has no elements???",
+                );
+                result.push((doc, word));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Draws random sentences from the database and saves them as a named batch, overwriting the previous batch with the same name. An empty draw is an error and never overwrites anything.
+    /// @param name - the name of the batch to save the draw as.
+    /// @param count - how many sentences to draw. Less are returned if there aren't enough sentences to draw from.
+    /// @param min_easiness - if Some, only the sentences with a rating of at least this value are drawn. Found through a range scan on the rankings index.
+    /// @param unseen_only - if true, only draws the sentences that haven't been drawn with this flag before, and remembers the drawn ones as seen.
+    /// @returns - the drawn sentences in the order they were drawn.
+    pub fn random_batch(
+        &mut self,
+        name: &str,
+        count: u32,
+        min_easiness: Option<u64>,
+        unseen_only: bool,
+    ) -> Result<Vec<SentenceDoc>> {
+        self.ensure_index_consistency(
+            SENTENCE_RANK_INDEX,
+            SENTENCES,
+            Self::index_inconsistent,
+            Self::build_rankings_index,
+        )?;
+
+        // NOTE: the draw, the seen history update and the batch itself are all written in the same write transaction, so that two runs can never draw the same "unseen" sentence.
+        let wtx = self.db.begin_write()?;
+        let result;
+        {
+            let index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+            let primary_table = wtx.open_table(SENTENCES)?;
+            let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
+            let mut batch_table = wtx.open_table(BATCHES)?;
+
+            let batch = BatchDoc::new(
+                Self::draw_random(
+                    &index_table,
+                    &mut seen_table,
+                    count as usize,
+                    min_easiness,
+                    unseen_only,
+                    &HashSet::new(),
+                )?,
+                min_easiness,
+                unseen_only,
+            );
+            if batch.hashes.is_empty() {
+                bail!(
+                    "SageDatabase::random_batch(): there are no sentences left to draw with these settings, the batch `{}` is kept as it was.{}",
+                    name,
+                    if unseen_only {
+                        " Every sentence has been shown already, see `--forget-seen`."
+                    } else {
+                        ""
+                    }
+                );
+            }
+            result = Self::batch_docs(&primary_table, &batch)?;
+            batch_table.insert(name, batch)?;
+        }
+        wtx.commit()?;
+
+        Ok(result)
+    }
+
+    /// Replaces only the specific sentences of a saved batch with fresh random draws, keeping the rest as is. The fresh sentences are drawn with the same settings the batch was originally drawn with, and are never the ones already in the batch.
+    /// @param name - the name of the saved batch.
+    /// @param positions - 1-based positions of the sentences to replace, as `batch()` shows them.
+    /// @returns - the whole updated batch, and the number of sentences that could actually be replaced (less than requested if the database ran out of sentences to draw).
+    pub fn regen_batch(
+        &mut self,
+        name: &str,
+        positions: &[usize],
+    ) -> Result<(Vec<SentenceDoc>, usize)> {
+        self.ensure_index_consistency(
+            SENTENCE_RANK_INDEX,
+            SENTENCES,
+            Self::index_inconsistent,
+            Self::build_rankings_index,
+        )?;
+
+        let wtx = self.db.begin_write()?;
+        let result;
+        let replaced;
+        {
+            let index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+            let primary_table = wtx.open_table(SENTENCES)?;
+            let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
+            let mut batch_table = wtx.open_table(BATCHES)?;
+
+            let mut batch = batch_table
+                .get(name)?
+                .map(|guard| guard.value())
+                .context(format!(
+                    "SageDatabase::regen_batch(): there's no batch called `{}`. Draw it with the `random` command first.",
+                    name
+                ))?;
+            // NOTE: the sentences removed from the database since the batch was saved aren't shown by `batch()`, so they're dropped before the positions are looked up, to keep them the positions the user has been shown.
+            let mut live_hashes = Vec::with_capacity(batch.hashes.len());
+            for hash in batch.hashes {
+                if primary_table.get(&hash)?.is_some() {
+                    live_hashes.push(hash);
+                }
+            }
+            batch.hashes = live_hashes;
+            if let Some(p) = positions
+                .iter()
+                .find(|p| **p == 0 || **p > batch.hashes.len())
+            {
+                bail!(
+                    "SageDatabase::regen_batch(): position {} is out of the batch `{}`, which has sentences 1 to {}.",
+                    p,
+                    name,
+                    batch.hashes.len()
+                );
+            }
+
+            let mut unique_positions = positions.to_vec();
+            unique_positions.sort_unstable();
+            unique_positions.dedup();
+            let in_batch: HashSet<[u8; 32]> = batch.hashes.iter().copied().collect();
+            let fresh = Self::draw_random(
+                &index_table,
+                &mut seen_table,
+                unique_positions.len(),
+                batch.min_easiness,
+                batch.unseen_only,
+                &in_batch,
+            )?;
+            replaced = fresh.len();
+            for (position, hash) in unique_positions.iter().zip(fresh) {
+                batch.hashes[position - 1] = hash;
+            }
+
+            result = Self::batch_docs(&primary_table, &batch)?;
+            batch_table.insert(name, batch)?;
+        }
+        wtx.commit()?;
+
+        Ok((result, replaced))
+    }
+
+    /// Fetches the sentences of a saved batch.
+    /// @returns - the sentences in the batch order, None if there's no batch with this name.
+    pub fn batch(&self, name: &str) -> Result<Option<Vec<SentenceDoc>>> {
+        let rtx = self.db.begin_read()?;
+        let primary_table = rtx.open_table(SENTENCES)?;
+        let batch_table = rtx.open_table(BATCHES)?;
+
+        match batch_table.get(name)? {
+            Some(guard) => Ok(Some(Self::batch_docs(&primary_table, &guard.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Randomly picks hashes of the sentences from the rankings index (partial Fisher-Yates shuffle of all the candidates).
+    /// @param exclude - hashes that must not be drawn.
+    /// @returns - up to `count` hashes, marked as seen if `unseen_only` is true.
+    fn draw_random(
+        index_table: &impl ReadableTable<(u64, [u8; 32]), ()>,
+        seen_table: &mut redb::Table<[u8; 32], ()>,
+        count: usize,
+        min_easiness: Option<u64>,
+        unseen_only: bool,
+        exclude: &HashSet<[u8; 32]>,
+    ) -> Result<Vec<[u8; 32]>> {
+        let mut candidates = vec![];
+        for row in index_table.range(ValueBounds::new(None, min_easiness).key_range())? {
+            let (key_guard, _) = row?;
+            let (_, hash) = key_guard.value();
+            if exclude.contains(&hash) {
+                continue;
+            }
+            if !unseen_only || seen_table.get(&hash)?.is_none() {
+                candidates.push(hash);
+            }
+        }
+
+        let mut rng = fastrand::Rng::new();
+        let drawn = count.min(candidates.len());
+        for i in 0..drawn {
+            let j = rng.usize(i..candidates.len());
+            candidates.swap(i, j);
+        }
+        candidates.truncate(drawn);
+
+        if unseen_only {
+            for hash in &candidates {
+                seen_table.insert(hash, ())?;
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Looks up the sentences of a batch. Sentences that have been removed from the database since the batch was drawn are skipped.
+    fn batch_docs(
+        primary_table: &impl ReadableTable<[u8; 32], SentenceDoc>,
+        batch: &BatchDoc,
+    ) -> Result<Vec<SentenceDoc>> {
+        let mut docs = Vec::with_capacity(batch.hashes.len());
+        for hash in &batch.hashes {
+            if let Some(guard) = primary_table.get(hash)? {
+                docs.push(guard.value());
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Forgets every sentence the `random` command has shown, so all of them can be drawn again.
+    /// @returns - the number of sentences forgotten.
+    pub fn forget_seen(&mut self) -> Result<u64> {
+        let wtx = self.db.begin_write()?;
+        let forgotten;
+        {
+            let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
+            forgotten = seen_table.len()?;
+            seen_table.retain(|_, _| false)?;
+        }
+        wtx.commit()?;
+
+        Ok(forgotten)
+    }
+
+    fn version(db: &Database) -> Result<u32> {
+        let rtx = db.begin_read()?;
+        let systb = rtx.open_table(SYSTEM)?;
+        if systb.len()? > 1 {
+            panic!(
+                "SageDatabase::ensure_version_consistency(): SYSTEM table has more than one record, but is supposed to only have one, where all the config variables are stored. This may mean that the data in the DB is inconsistent, or something went horribly wrong."
+            );
+        }
+        Ok(systb
+            .first()?
+            .map(|(ver_guard, _)| ver_guard.value())
+            .unwrap_or(0))
+    }
+
+    /// Static method that compares the version constant in the code and the version number in the database, and upgrades a database of an older version: its sentences are re-keyed and every index is rebuilt, all in a single write transaction.
+    /// @param db - the database to perform the check on.
+    /// @param lang - the language of the sentences stored in the database, needed to split them into words again, see `rehash_sentences()`.
+    /// @returns - Ok(()) if the database is (now) of the current version, Err(_) if IO errors.
+    fn ensure_version_consistency(db: &Database, lang: &str) -> Result<()> {
+        let current_version = Self::version(db)?;
+
+        match current_version.cmp(&(REDB_LAYOUT_VERSION).into()) {
+            // NOTE: if we've extracted a certain version from the DB, and it's exactly equal to the current DB layout version in our code, that means the version is up-to-date and we can safely return and do nothing.
+            Ordering::Equal => Ok(()),
+            Ordering::Less => {
+                let wtx = db.begin_write()?;
+                {
+                    let mut systb = wtx.open_table(SYSTEM)?;
+                    if current_version != 0 {
+                        systb.remove(current_version)?;
+                        Self::rehash_sentences(&wtx, lang)?;
+                        // NOTE: the indexes reference the old hashes, so they're rebuilt in the same transaction: an upgrade that gets interrupted leaves the old layout as it was, never the new one with stale indexes.
+                        Self::fill_freq_index(&wtx)?;
+                        Self::fill_rankings_index(&wtx)?;
+                        Self::fill_word_sentence_index(&wtx, lang)?;
+                    }
+                    systb.insert(REDB_LAYOUT_VERSION as u32, ())?;
+                }
+                wtx.commit()?;
+                Ok(())
+            }
+            Ordering::Greater => panic!(
+                "SageDatabase::ensure_version_consistency(): the DB version in the code is smaller than the version in the database itself. This means an inconsistency between the code and the DB, this needs to be investigated separately - it shouldn't happen if FreQ Sage is behaving properly."
+            ),
+        }
+    }
+
+    /// Sentence hashes are seeded with the layout version (see `Util::hash_words()`) and made from the words of the sentence (see `Tokenizer::words()`), so after a version bump every sentence has to be re-keyed with its new hash.
+    /// The seen history, the source links and the saved batches are re-keyed together with the sentences, and every sentence is rescored with its words as they're split now. The sentences left with too few words to be ranked are dropped, together with everything referencing them. The indexes referencing the old hashes have to be rebuilt by the caller, in the same write transaction.
+    /// NOTE: the word frequencies can't be recounted without the trained texts, so they stay as they were counted by the older version until the texts are trained on again.
+    fn rehash_sentences(wtx: &WriteTransaction, lang: &str) -> Result<()> {
+        let tokenizer = Tokenizer::new(lang);
+        let mut rehashed: HashMap<[u8; 32], [u8; 32]> = HashMap::new();
+        {
+            let freq_table = wtx.open_table(FREQUENCIES)?;
+            let mut sentence_table = wtx.open_table(SENTENCES)?;
+            let mut freq_cache: HashMap<String, u64> = HashMap::new();
+            let stored = sentence_table
+                .iter()?
+                .map(|row| {
+                    row.map(|(hash_guard, doc_guard)| (hash_guard.value(), doc_guard.value()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            sentence_table.retain(|_, _| false)?;
+            for (old_hash, mut doc) in stored {
+                let words = tokenizer.words(&doc.raw);
+                if words.len() < WORDS_IN_SENTENCE_DISCARD_THRESHOLD as usize {
+                    continue;
+                }
+                Self::cache_freqs(&freq_table, &words, &mut freq_cache)?;
+                doc.rating = SentenceRanker::score_words(&words, &freq_cache);
+                let hash = Util::hash_words(&words);
+                sentence_table.insert(hash, doc)?;
+                rehashed.insert(old_hash, hash);
+            }
+        }
+
+        let mut seen_table = wtx.open_table(SEEN_SENTENCES)?;
+        let seen = seen_table
+            .iter()?
+            .map(|row| row.map(|(hash_guard, _)| hash_guard.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        seen_table.retain(|_, _| false)?;
+        for hash in seen.iter().filter_map(|hash| rehashed.get(hash)) {
+            seen_table.insert(hash, ())?;
+        }
+
+        let mut sentence_source_table = wtx.open_table(SENTENCE_SOURCES)?;
+        let links = sentence_source_table
+            .iter()?
+            .map(|row| {
+                row.map(|(key_guard, location_guard)| {
+                    (key_guard.value(), location_guard.value().to_owned())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        sentence_source_table.retain(|_, _| false)?;
+        for ((hash, id), location) in links {
+            if let Some(hash) = rehashed.get(&hash) {
+                sentence_source_table.insert((*hash, id), location.as_str())?;
+            }
+        }
+
+        let mut batch_table = wtx.open_table(BATCHES)?;
+        let batches = batch_table
+            .iter()?
+            .map(|row| {
+                row.map(|(name_guard, batch_guard)| {
+                    (name_guard.value().to_owned(), batch_guard.value())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, mut batch) in batches {
+            batch.hashes = batch
+                .hashes
+                .iter()
+                .filter_map(|hash| rehashed.get(hash).copied())
+                .collect();
+            batch_table.insert(name.as_str(), batch)?;
+        }
+
+        Ok(())
+    }
+
+    /// Index consistency check for the 1:1 indexes: every primary record has exactly one index record.
+    fn index_inconsistent(index_len: u64, primary_len: u64) -> bool {
+        index_len != primary_len
+    }
+
+    /// Index consistency check for the inverted word index: one sentence has as many index records as it has unique words, so only an empty index for a non-empty primary table (or vice versa) can be spotted.
+    fn inverted_index_inconsistent(index_len: u64, primary_len: u64) -> bool {
+        (index_len == 0) != (primary_len == 0)
+    }
+
+    /// Removes the index records and the seen history of a sentence that has just been removed from the primary sentences table.
+    fn remove_sentence_records(
+        rank_index_table: &mut redb::Table<(u64, [u8; 32]), ()>,
+        word_index_table: &mut redb::Table<([u8; 32], [u8; 32]), ()>,
+        seen_table: &mut redb::Table<[u8; 32], ()>,
+        tokenizer: &Tokenizer,
+        hash: [u8; 32],
+        doc: &SentenceDoc,
+    ) -> Result<()> {
+        rank_index_table.remove(&(u64::MAX - doc.rating, hash))?;
+        for word_hash in Self::unique_word_hashes(&tokenizer.words(&doc.raw)) {
+            word_index_table.remove(&(word_hash, hash))?;
+        }
+        seen_table.remove(&hash)?;
+
+        Ok(())
+    }
+
+    /// Collects the hashes of all the sentences that contain the word from the inverted word index.
+    fn sentence_hashes_with_word(
+        word_index_table: &impl ReadableTable<([u8; 32], [u8; 32]), ()>,
+        word_hash: [u8; 32],
+    ) -> Result<HashSet<[u8; 32]>> {
+        let mut hashes = HashSet::new();
+        for row in word_index_table.range((word_hash, [0u8; 32])..=(word_hash, [u8::MAX; 32]))? {
+            let (key_guard, _) = row?;
+            hashes.insert(key_guard.value().1);
+        }
+
+        Ok(hashes)
+    }
+
+    /// Sorts the sentences by their rating, the easiest first, and cuts off everything past the limit.
+    fn easiest_first(mut docs: Vec<SentenceDoc>, maybe_limit: Option<u32>) -> Vec<SentenceDoc> {
+        docs.sort_by_key(|doc| u64::MAX - doc.rating);
+        if let Some(limit) = maybe_limit {
+            docs.truncate(limit as usize);
+        }
+        docs
+    }
+
+    /// Shared paging for the (inverted value, hash) indexes: a range scan over the value bounds, skipping `offset` keys from the start of the range.
+    /// Only the index keys are read here, the primary tables are looked up by the caller for the returned page only.
+    /// @returns - pairs of (real rank in the whole index, starting from 1; hash of the primary record).
+    fn index_page(
+        index_table: &impl ReadableTable<(u64, [u8; 32]), ()>,
+        bounds: ValueBounds,
+        offset: u64,
+        maybe_limit: Option<u32>,
+    ) -> Result<Vec<(u64, [u8; 32])>> {
+        if bounds.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let range = bounds.key_range();
+        // NOTE: redb can't tell the position of a key in a table, so the rank of the first key in the range has to be counted.
+        let rank_base = if bounds.at_most.is_some() {
+            index_table.range(..*range.start())?.count() as u64
+        } else {
+            0
+        };
+
+        let limit = maybe_limit.map(|l| l as usize).unwrap_or(usize::MAX);
+        let mut page = Vec::with_capacity(maybe_limit.unwrap_or(0) as usize);
+        for (position, row) in index_table
+            .range(range)?
+            .enumerate()
+            .skip(offset.try_into().unwrap_or(usize::MAX))
+            .take(limit)
+        {
+            let (key_guard, _) = row?;
+            let (_, hash) = key_guard.value();
+            page.push((rank_base + position as u64 + 1, hash));
+        }
+
+        Ok(page)
+    }
+
+    /// Looks up the stored frequencies of the words that aren't in the cache yet. Words missing from the database get a frequency of 0.
+    fn cache_freqs(
+        freq_table: &impl ReadableTable<[u8; 32], FrequencyDoc>,
+        words: &[String],
+        freq_cache: &mut HashMap<String, u64>,
+    ) -> Result<()> {
+        for word in words {
+            if !freq_cache.contains_key(word) {
+                let freq = freq_table
+                    .get(&FrequencyDoc::new(word.clone(), 0).hash())?
+                    .map(|guard| guard.value().freq)
+                    .unwrap_or(0);
+                freq_cache.insert(word.clone(), freq);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hashes every unique word in the slice the same way `FrequencyDoc::hash()` does.
+    fn unique_word_hashes(words: &[String]) -> HashSet<[u8; 32]> {
+        words
+            .iter()
+            .map(|w| FrequencyDoc::new(w.clone(), 0).hash())
+            .collect()
+    }
+
+    /// Rebuilds an index for a primary table if one of these conditions is true:
+    /// - Index table doesn't exist;
+    /// - `lengths_inconsistent` returns true for the index table and primary table lengths.
+    ///
+    /// A version upgrade rebuilds every index by itself, see `ensure_version_consistency()`.
+    ///   @returns - Ok(()) if all went well, Err(_) if IO errors.
+    fn ensure_index_consistency<KI, VI, KP, VP, C, F>(
+        &mut self,
+        index_table_def: TableDefinition<KI, VI>,
+        primary_table_def: TableDefinition<KP, VP>,
+        lengths_inconsistent: C,
+        index_build_func: F,
+    ) -> Result<()>
+    where
+        KI: redb::Key,
+        KP: redb::Key,
+        VI: redb::Value,
+        VP: redb::Value,
+        C: FnOnce(u64, u64) -> bool,
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let rtx = self.db.begin_read()?;
+        let need_rebuild = match rtx.open_table(index_table_def) {
+            Ok(index_table) => {
+                let primary_table = rtx.open_table(primary_table_def)?;
+                lengths_inconsistent(index_table.len()?, primary_table.len()?)
+            }
+            Err(_) => true,
+        };
+        if need_rebuild {
+            index_build_func(self)
+        } else {
+            Ok(())
+        }
+    }
+
+    // REFACTOR: [???] can build_freq_index() and build_rankings_index() be merged into a single function?
+    fn build_freq_index(&mut self) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        Self::fill_freq_index(&wtx)?;
+        wtx.commit()?;
+
+        Ok(())
+    }
+
+    /// The body of `build_freq_index()`, inside an already open write transaction.
+    fn fill_freq_index(wtx: &WriteTransaction) -> Result<()> {
+        let primary_table = wtx.open_table(FREQUENCIES)?;
+        wtx.delete_table(WORD_FREQ_INDEX)?;
+        let mut index_table = wtx.open_table(WORD_FREQ_INDEX)?;
+        for primary_pair in primary_table.iter()? {
+            let (hash_guard, freq_doc_guard) = primary_pair?;
+            let hash = hash_guard.value();
+            let doc_freq = freq_doc_guard.value().freq;
+            index_table.insert((u64::MAX - doc_freq, hash), ())?;
+        }
+
+        Ok(())
+    }
+
+    fn build_rankings_index(&mut self) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        Self::fill_rankings_index(&wtx)?;
+        wtx.commit()?;
+
+        Ok(())
+    }
+
+    /// The body of `build_rankings_index()`, inside an already open write transaction.
+    fn fill_rankings_index(wtx: &WriteTransaction) -> Result<()> {
+        let primary_table = wtx.open_table(SENTENCES)?;
+        wtx.delete_table(SENTENCE_RANK_INDEX)?;
+        let mut index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+        for primary_pair in primary_table.iter()? {
+            let (hash_guard, sentence_doc_guard) = primary_pair?;
+            let hash = hash_guard.value();
+            let ranking = sentence_doc_guard.value().rating;
+            index_table.insert((u64::MAX - ranking, hash), ())?;
+        }
+
+        Ok(())
+    }
+
+    fn build_word_sentence_index(&mut self) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        Self::fill_word_sentence_index(&wtx, &self.lang)?;
+        wtx.commit()?;
+
+        Ok(())
+    }
+
+    /// The body of `build_word_sentence_index()`, inside an already open write transaction.
+    fn fill_word_sentence_index(wtx: &WriteTransaction, lang: &str) -> Result<()> {
+        let primary_table = wtx.open_table(SENTENCES)?;
+        wtx.delete_table(WORD_SENTENCE_INDEX)?;
+        let mut index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+        let tokenizer = Tokenizer::new(lang);
+        for primary_pair in primary_table.iter()? {
+            let (hash_guard, sentence_doc_guard) = primary_pair?;
+            let hash = hash_guard.value();
+            let words = tokenizer.words(&sentence_doc_guard.value().raw);
+            for word_hash in Self::unique_word_hashes(&words) {
+                index_table.insert((word_hash, hash), ())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A new database in the temp directory, named after the test so that the tests running in parallel never share one.
+    fn temp_db(test: &str) -> Result<SageDatabase> {
+        let path =
+            std::env::temp_dir().join(format!("freq-sage-{}-{}.redb", test, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        SageDatabase::new(&path, "English", true)
+    }
+
+    /// Trains on a text as a single source, in a training run of its own.
+    /// @returns - the id of the source.
+    fn train_text(db: &mut SageDatabase, path: &str, text: &str) -> Result<u64> {
+        let mut data = RawData::new(db.lang.clone());
+        for sentence in Util::sentences_from_text(text, &db.lang) {
+            data.push(sentence, String::new());
+        }
+        let mut run = db.begin_training()?;
+        let id = run.begin_source(path.to_owned())?;
+        run.train(id, &data)?;
+        run.finish_source(
+            id,
+            *blake3::hash(text.as_bytes()).as_bytes(),
+            data.sentences.len() as u64,
+            Some((text.len() as u64, 0)),
+        )?;
+        run.commit()?;
+
+        Ok(id)
+    }
+
+    /// Pairs of (word or sentence; its frequency or rating).
+    type Values = Vec<(String, u64)>;
+
+    /// All the frequencies and sentence ratings in the database, from the most frequent word and the easiest sentence.
+    fn snapshot(db: &mut SageDatabase) -> Result<(Values, Values)> {
+        let freqs = db
+            .top_freqs(ValueBounds::new(None, None), 0, None)?
+            .into_iter()
+            .map(|(_, doc)| (doc.word, doc.freq))
+            .collect();
+        let ratings = db
+            .top_rankings(ValueBounds::new(None, None), 0, None)?
+            .into_iter()
+            .map(|(_, doc)| (doc.raw, doc.rating))
+            .collect();
+
+        Ok((freqs, ratings))
+    }
+
+    /// Checks that every stored sentence is rated by the current frequencies, the way a sentence trained on right now would be.
+    fn assert_rated_by_freqs(db: &mut SageDatabase) -> Result<()> {
+        let (freqs, ratings) = snapshot(db)?;
+        let freqs: HashMap<String, u64> = freqs.into_iter().collect();
+        let tokenizer = Tokenizer::new(&db.lang);
+        for (raw, rating) in ratings {
+            assert_eq!(
+                rating,
+                SentenceRanker::score_words(&tokenizer.words(&raw), &freqs),
+                "`{}` has a stale rating",
+                raw
+            );
+        }
+
+        Ok(())
+    }
+
+    fn remove(db: SageDatabase) -> Result<()> {
+        let path = db.path.clone();
+        drop(db);
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn older_layout_is_upgraded() -> Result<()> {
+        let db = temp_db("upgrade")?;
+        let path = db.path.clone();
+        let tokenizer = Tokenizer::new("English");
+        let raws = [
+            "The old man walked home.",
+            "The man ran home quickly.",
+            "Hi there.",
+        ];
+
+        // NOTE: a database of the previous layout: sentences and everything referencing them are keyed by hashes the current version doesn't make.
+        {
+            let wtx = db.db.begin_write()?;
+            {
+                let mut freq_table = wtx.open_table(FREQUENCIES)?;
+                for word in raws.iter().flat_map(|raw| tokenizer.words(raw)) {
+                    let doc = FrequencyDoc::new(word, 1);
+                    freq_table.insert(doc.hash(), doc)?;
+                }
+                let mut sentence_table = wtx.open_table(SENTENCES)?;
+                let mut rank_index_table = wtx.open_table(SENTENCE_RANK_INDEX)?;
+                let mut word_index_table = wtx.open_table(WORD_SENTENCE_INDEX)?;
+                for (i, raw) in raws.iter().enumerate() {
+                    let old_hash = [i as u8 + 1; 32];
+                    sentence_table.insert(old_hash, SentenceDoc::new(raw.to_string(), 0))?;
+                    rank_index_table.insert((u64::MAX, old_hash), ())?;
+                    for word_hash in SageDatabase::unique_word_hashes(&tokenizer.words(raw)) {
+                        word_index_table.insert((word_hash, old_hash), ())?;
+                    }
+                }
+                wtx.open_table(SEEN_SENTENCES)?.insert([1u8; 32], ())?;
+                let mut systb = wtx.open_table(SYSTEM)?;
+                systb.remove(REDB_LAYOUT_VERSION as u32)?;
+                systb.insert(REDB_LAYOUT_VERSION as u32 - 1, ())?;
+            }
+            wtx.commit()?;
+        }
+        drop(db);
+
+        let mut db = SageDatabase::new(&path, "English", false)?;
+        assert_eq!(SageDatabase::version(&db.db)?, REDB_LAYOUT_VERSION as u32);
+        {
+            let rtx = db.db.begin_read()?;
+            let sentence_table = rtx.open_table(SENTENCES)?;
+            let hashes: HashSet<[u8; 32]> = raws[..2]
+                .iter()
+                .map(|raw| Util::hash_words(&tokenizer.words(raw)))
+                .collect();
+            let stored: HashSet<[u8; 32]> = sentence_table
+                .iter()?
+                .map(|row| row.map(|(hash_guard, _)| hash_guard.value()))
+                .collect::<Result<_, _>>()?;
+            // NOTE: the sentence too short to be ranked is dropped.
+            assert_eq!(stored, hashes);
+
+            let rank_index_table = rtx.open_table(SENTENCE_RANK_INDEX)?;
+            let ranked: HashSet<[u8; 32]> = rank_index_table
+                .iter()?
+                .map(|row| row.map(|(key_guard, _)| key_guard.value().1))
+                .collect::<Result<_, _>>()?;
+            assert_eq!(ranked, hashes);
+
+            let word_index_table = rtx.open_table(WORD_SENTENCE_INDEX)?;
+            for row in word_index_table.iter()? {
+                assert!(hashes.contains(&row?.0.value().1));
+            }
+
+            let seen_table = rtx.open_table(SEEN_SENTENCES)?;
+            assert!(
+                seen_table
+                    .get(Util::hash_words(&tokenizer.words(raws[0])))?
+                    .is_some()
+            );
+            assert_eq!(seen_table.len()?, 1);
+        }
+
+        assert_eq!(
+            db.top_rankings(ValueBounds::new(None, None), 0, None)?
+                .len(),
+            2
+        );
+        let with_word = db.sentences_with_word("walked", None)?;
+        assert_eq!(with_word.len(), 1);
+        assert_eq!(with_word[0].raw, raws[0]);
+
+        remove(db)
+    }
+
+    #[test]
+    fn known_words_are_case_insensitive() -> Result<()> {
+        let mut db = temp_db("i-plus-one")?;
+        train_text(
+            &mut db,
+            "mat.txt",
+            "The cat sat on the mat. The cat sat on a hat.",
+        )?;
+
+        let the_cat_sat_on: Vec<String> = ["the", "cat", "sat", "on"].map(String::from).to_vec();
+        db.mark_known(&the_cat_sat_on, false)?;
+        let found = db.i_plus_one(ValueBounds::default(), 0, None)?;
+        let found: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(doc, word)| (doc.raw.as_str(), word.as_str()))
+            .collect();
+        // NOTE: the sentence-initial `The` is known too, and `a hat` is two unknown words.
+        assert_eq!(found, [("The cat sat on the mat.", "mat")]);
+
+        assert_eq!(db.mark_unknown(&["The".to_owned()])?, 1);
+        assert!(db.i_plus_one(ValueBounds::default(), 0, None)?.is_empty());
+
+        remove(db)
+    }
+
+    #[test]
+    fn regenerated_positions_are_the_shown_ones() -> Result<()> {
+        let mut db = temp_db("regen")?;
+        train_text(
+            &mut db,
+            "pets.txt",
+            "The cat sat on the mat. The dog sat on the rug. The bird sat on the branch. The fish swam in the bowl. The horse ran in the field.",
+        )?;
+
+        let drawn = db.random_batch("pets", 3, None, false)?;
+        let removed = drawn[0].raw.clone();
+        db.clean(&Regex::new(&regex::escape(&removed))?, false, false)?;
+        let shown: Vec<String> = db
+            .batch("pets")?
+            .context("the batch is saved")?
+            .into_iter()
+            .map(|doc| doc.raw)
+            .collect();
+        assert_eq!(shown, [drawn[1].raw.clone(), drawn[2].raw.clone()]);
+
+        let (regenerated, replaced) = db.regen_batch("pets", &[1])?;
+        assert_eq!(replaced, 1);
+        assert_eq!(regenerated.len(), 2);
+        assert_eq!(regenerated[1].raw, shown[1]);
+        assert!(![&removed, &shown[0], &shown[1]].contains(&&regenerated[0].raw));
+        assert!(db.regen_batch("pets", &[3]).is_err());
+
+        remove(db)
+    }
+
+    #[test]
+    fn empty_draws_keep_the_saved_batch() -> Result<()> {
+        let mut db = temp_db("empty-draw")?;
+        train_text(
+            &mut db,
+            "pets.txt",
+            "The cat sat on the mat. The dog sat on the rug.",
+        )?;
+
+        let drawn = db.random_batch("pets", 5, None, true)?;
+        assert_eq!(drawn.len(), 2);
+        assert!(db.random_batch("pets", 5, None, true).is_err());
+        assert_eq!(db.batch("pets")?.context("the batch is saved")?.len(), 2);
+
+        db.forget_seen()?;
+        assert_eq!(db.random_batch("pets", 5, None, true)?.len(), 2);
+
+        remove(db)
+    }
+
+    #[test]
+    fn source_ids_are_never_reused() -> Result<()> {
+        let mut db = temp_db("source-ids")?;
+        assert_eq!(
+            train_text(&mut db, "cat.txt", "The cat sat on the mat.")?,
+            1
+        );
+        assert_eq!(
+            train_text(&mut db, "dog.txt", "The dog sat on the rug.")?,
+            2
+        );
+        db.untrain(2)?;
+        assert_eq!(
+            train_text(&mut db, "bird.txt", "The bird sat on the branch.")?,
+            3
+        );
+
+        let sources: Vec<(u64, String)> = db
+            .sources()?
+            .into_iter()
+            .map(|(id, source)| (id, source.path))
+            .collect();
+        assert_eq!(
+            sources,
+            [(1, "cat.txt".to_owned()), (3, "bird.txt".to_owned())]
+        );
+        let linked: Vec<u64> = db
+            .sources_of_sentence("The bird sat on the branch.")?
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect();
+        assert_eq!(linked, [3]);
+
+        remove(db)
+    }
+
+    #[test]
+    fn duplicates_are_found_and_taken_back() -> Result<()> {
+        let mut db = temp_db("duplicates")?;
+        let text = "The cat sat on the mat. The dog sat on the rug.";
+        train_text(&mut db, "pets.txt", text)?;
+        let before = snapshot(&mut db)?;
+        let content_hash = *blake3::hash(text.as_bytes()).as_bytes();
+        let stamp = (text.len() as u64, 0);
+
+        let mut run = db.begin_training()?;
+        let found = |found: Option<(u64, SourceDoc)>| found.map(|(id, _)| id);
+        assert_eq!(found(run.source_by_stamp("pets.txt", stamp)?), Some(1));
+        assert_eq!(found(run.source_by_stamp("pets.txt", (stamp.0, 1))?), None);
+        assert_eq!(found(run.source_by_stamp("copy.txt", stamp)?), None);
+        assert_eq!(found(run.source_by_content(&content_hash)?), Some(1));
+
+        // A copy is only recognized after being trained on, so it's taken back.
+        let mut data = RawData::new(db.lang.clone());
+        for sentence in Util::sentences_from_text(text, &db.lang) {
+            data.push(sentence, String::new());
+        }
+        let id = run.begin_source("copy.txt".to_owned())?;
+        run.train(id, &data)?;
+        run.discard_source(id)?;
+        assert_eq!(run.commit()?, 0);
+        assert_eq!(snapshot(&mut db)?, before);
+        assert_eq!(db.sources()?.len(), 1);
+
+        let mut run = db.begin_training()?;
+        let id = run.begin_source("birds.txt".to_owned())?;
+        run.train(id, &data)?;
+        run.abort()?;
+        assert_eq!(snapshot(&mut db)?, before);
+        assert_eq!(db.sources()?.len(), 1);
+
+        remove(db)
+    }
+
+    #[test]
+    fn training_rescores_stored_sentences() -> Result<()> {
+        let mat = "The cat sat on the mat.";
+        let black = "The cat is black. ".repeat(20);
+
+        let mut db = temp_db("rescoring")?;
+        train_text(&mut db, "mat.txt", mat)?;
+        let rating = |db: &mut SageDatabase| -> Result<u64> {
+            Ok(db.sentences_with_phrase(&["mat".to_owned()], None)?[0].rating)
+        };
+        let before = rating(&mut db)?;
+        train_text(&mut db, "black.txt", &black)?;
+        assert!(rating(&mut db)? > before);
+        assert_rated_by_freqs(&mut db)?;
+
+        // The stored ratings don't depend on the order the texts were trained in.
+        let mut reversed = temp_db("rescoring-reversed")?;
+        train_text(&mut reversed, "black.txt", &black)?;
+        train_text(&mut reversed, "mat.txt", mat)?;
+        assert_eq!(snapshot(&mut db)?, snapshot(&mut reversed)?);
+
+        remove(reversed)?;
+        remove(db)
+    }
+
+    #[test]
+    fn search_finds_words_phrases_and_patterns() -> Result<()> {
+        let mut db = temp_db("search")?;
+        train_text(
+            &mut db,
+            "pets.txt",
+            "The cat sat on the mat. The dog sat on the cat. A black cat was sleeping.",
+        )?;
+        let raws = |docs: Vec<SentenceDoc>| -> Vec<String> {
+            let mut raws: Vec<String> = docs.into_iter().map(|doc| doc.raw).collect();
+            raws.sort();
+            raws
+        };
+        let phrase =
+            |phrase: &str| -> Vec<String> { phrase.split(' ').map(str::to_owned).collect() };
+
+        assert_eq!(
+            raws(db.sentences_with_word("cat", None)?),
+            [
+                "A black cat was sleeping.",
+                "The cat sat on the mat.",
+                "The dog sat on the cat."
+            ]
+        );
+        assert!(db.sentences_with_word("bird", None)?.is_empty());
+        assert_eq!(db.sentences_with_word("cat", Some(2))?.len(), 2);
+
+        // Every word of a phrase has to be there, next to each other and in order.
+        assert_eq!(
+            raws(db.sentences_with_phrase(&phrase("sat on the"), None)?),
+            ["The cat sat on the mat.", "The dog sat on the cat."]
+        );
+        assert_eq!(
+            raws(db.sentences_with_phrase(&phrase("the cat"), None)?),
+            ["The dog sat on the cat."]
+        );
+        assert!(
+            db.sentences_with_phrase(&phrase("cat the"), None)?
+                .is_empty()
+        );
+
+        assert_eq!(
+            raws(db.sentences_matching(&Regex::new(r"^The \w+ sat")?, None)?),
+            ["The cat sat on the mat.", "The dog sat on the cat."]
+        );
+        let easiest = db.sentences_matching(&Regex::new("cat")?, None)?;
+        assert!(easiest.windows(2).all(|w| w[0].rating >= w[1].rating));
+
+        remove(db)
+    }
+
+    #[test]
+    fn pages_keep_the_real_ranks() -> Result<()> {
+        let mut db = temp_db("paging")?;
+        train_text(
+            &mut db,
+            "counts.txt",
+            "four four four four. three three three. two two. one. The cat sat on the mat. The dog slept on the rug. A cat sat.",
+        )?;
+        let counted = ["four", "three", "two", "one"];
+        let freqs = |db: &mut SageDatabase, bounds: ValueBounds| -> Result<Vec<(u64, String)>> {
+            Ok(db
+                .top_freqs(bounds, 0, None)?
+                .into_iter()
+                .map(|(rank, doc)| (rank, doc.word))
+                .filter(|(_, word)| counted.contains(&word.as_str()))
+                .collect())
+        };
+        let rankings = |db: &mut SageDatabase,
+                        bounds: ValueBounds,
+                        offset: u64,
+                        limit: Option<u32>|
+         -> Result<Vec<(u64, String)>> {
+            Ok(db
+                .top_rankings(bounds, offset, limit)?
+                .into_iter()
+                .map(|(rank, doc)| (rank, doc.raw))
+                .collect())
+        };
+
+        let all = freqs(&mut db, ValueBounds::default())?;
+        assert_eq!(
+            all.iter()
+                .map(|(_, word)| word.as_str())
+                .collect::<Vec<&str>>(),
+            counted
+        );
+        assert_eq!(
+            freqs(&mut db, ValueBounds::new(Some(4), Some(4)))?,
+            all[..1]
+        );
+        assert_eq!(
+            freqs(&mut db, ValueBounds::new(Some(3), Some(2)))?,
+            all[1..3]
+        );
+        assert!(freqs(&mut db, ValueBounds::new(Some(1), Some(2)))?.is_empty());
+
+        let all = rankings(&mut db, ValueBounds::default(), 0, None)?;
+        assert_eq!(
+            all.iter().map(|(rank, _)| *rank).collect::<Vec<u64>>(),
+            (1..=all.len() as u64).collect::<Vec<u64>>()
+        );
+        assert_eq!(
+            rankings(&mut db, ValueBounds::default(), 1, Some(2))?,
+            all[1..3]
+        );
+        assert!(rankings(&mut db, ValueBounds::default(), all.len() as u64, None)?.is_empty());
+
+        // A page inside the bounds starts at the real rank of its first sentence, not at 1.
+        let hardest = db
+            .top_rankings(ValueBounds::default(), 0, None)?
+            .pop()
+            .unwrap()
+            .1
+            .rating;
+        let tail = rankings(&mut db, ValueBounds::new(Some(hardest), None), 0, None)?;
+        assert!(tail[0].0 > 1);
+        assert_eq!(tail, all[all.len() - tail.len()..]);
+
+        remove(db)
+    }
+
+    #[test]
+    fn untrain_restores_what_was_there_before() -> Result<()> {
+        let mut db = temp_db("untrain")?;
+        let shared = "The cat sat on the mat.";
+        train_text(
+            &mut db,
+            "cat.txt",
+            &format!("{} The cat was black.", shared),
+        )?;
+        let before = snapshot(&mut db)?;
+
+        let id = train_text(
+            &mut db,
+            "dog.txt",
+            &format!("{} {}", shared, "The dog sat on the rug. ".repeat(10)),
+        )?;
+        assert_ne!(snapshot(&mut db)?, before);
+
+        let (mut changed_words, removed_words, removed_sentences, rescored) = db.untrain(id)?;
+        changed_words.sort();
+        assert_eq!(changed_words, ["The", "cat", "mat", "on", "sat", "the"]);
+        assert_eq!(removed_words, 2);
+        assert_eq!(removed_sentences, 1);
+        assert!(rescored > 0);
+        assert_eq!(snapshot(&mut db)?, before);
+        assert!(db.sentences_with_word("dog", None)?.is_empty());
+        let linked: Vec<u64> = db
+            .sources_of_sentence(shared)?
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect();
+        assert_eq!(linked, [1]);
+
+        remove(db)
+    }
+
+    #[test]
+    fn clean_removes_matches_everywhere() -> Result<()> {
+        let mut db = temp_db("clean")?;
+        let lorem = "Lorem ipsum dolor sit amet.";
+        let mat = "The cat sat on the mat.";
+        train_text(
+            &mut db,
+            "cat.txt",
+            &format!("{} {}", lorem, format!("{} ", mat).repeat(10)),
+        )?;
+        let before = snapshot(&mut db)?;
+
+        let pattern = Regex::new("ipsum|^mat$")?;
+        let clean =
+            |db: &mut SageDatabase, dry_run: bool| -> Result<(Vec<String>, Vec<String>, u64)> {
+                let (sentences, mut words, rescored) = db.clean(&pattern, true, dry_run)?;
+                words.sort();
+                Ok((sentences, words, rescored))
+            };
+        let (sentences, words) = (
+            vec![lorem.to_owned()],
+            vec!["ipsum".to_owned(), "mat".to_owned()],
+        );
+        assert_eq!(clean(&mut db, true)?, (sentences.clone(), words.clone(), 0));
+        assert_eq!(snapshot(&mut db)?, before);
+        assert_eq!(clean(&mut db, false)?, (sentences, words, 1));
+        assert!(db.sentences_matching(&pattern, None)?.is_empty());
+        assert!(db.sentences_with_word("ipsum", None)?.is_empty());
+        assert!(db.sentences_with_word("mat", None)?.is_empty());
+        assert!(db.sources_of_sentence(lorem)?.is_empty());
+        let (freqs, ratings) = snapshot(&mut db)?;
+        assert!(
+            freqs
+                .iter()
+                .all(|(word, _)| word != "ipsum" && word != "mat")
+        );
+        // NOTE: the word counts of the removed sentences stay.
+        assert!(freqs.iter().any(|(word, _)| word == "Lorem"));
+        assert_eq!(ratings.len(), 1);
+        assert_rated_by_freqs(&mut db)?;
+
+        // The removed words are gone from the source's counts too, so untraining it leaves nothing behind.
+        db.untrain(1)?;
+        assert_eq!(snapshot(&mut db)?, (vec![], vec![]));
+
+        remove(db)
     }
 }
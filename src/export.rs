@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::cli::AnkiField;
+use crate::constants::{WORD_APOSTROPHES, WORD_HYPHENS};
+use crate::database::SentenceDoc;
+
+/// A single sentence about to become an Anki note. The target word is the word the card is supposed to teach, if there is one.
+/// The source is only filled in if the source field is exported, since it needs extra database lookups.
+#[derive(Debug)]
+pub struct AnkiNote {
+    pub sentence: SentenceDoc,
+    pub target: Option<String>,
+    pub source: String,
+}
+
+impl AnkiNote {
+    pub fn new(sentence: SentenceDoc, target: Option<String>) -> Self {
+        Self {
+            sentence,
+            target,
+            source: String::new(),
+        }
+    }
+
+    /// Builds the cloze deletion version of the sentence: the first standalone occurrence of the target word is wrapped into `{{c1::...}}`. The target is written the way the tokenizer writes words, so it matches any apostrophe or hyphen look-alike in the sentence, and soft hyphens inside the word (`don’t` for `don't`).
+    /// @returns - the sentence as is if there's no target word, None if the target word can't be found in it.
+    pub fn cloze(&self) -> Option<String> {
+        let Some(target) = &self.target else {
+            return Some(self.sentence.raw.clone());
+        };
+        let raw = &self.sentence.raw;
+        let found = Regex::new(&Self::word_pattern(target)).ok()?.find(raw)?;
+
+        Some(format!(
+            "{}{{{{c1::{}}}}}{}",
+            &raw[..found.start()],
+            found.as_str(),
+            &raw[found.end()..]
+        ))
+    }
+
+    /// A regex matching a word as the tokenizer writes it, see `Tokenizer::words()`.
+    fn word_pattern(word: &str) -> String {
+        let apostrophes: String = WORD_APOSTROPHES.iter().collect();
+        let hyphens: String = WORD_HYPHENS.iter().collect();
+        let letters: Vec<String> = word
+            .chars()
+            .map(|c| match c {
+                '\'' => format!("['{}]", apostrophes),
+                c if WORD_HYPHENS.contains(&c) => format!("[{}]", regex::escape(&hyphens)),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect();
+        // NOTE: \b is Unicode-aware in the regex crate, so this works for non-ASCII words as well. It's only asserted next to a letter or a digit, as elisions (`l'`) end with an apostrophe.
+        let boundary = |c: Option<char>| {
+            if c.is_some_and(char::is_alphanumeric) {
+                r"\b"
+            } else {
+                ""
+            }
+        };
+
+        format!(
+            "{}{}{}",
+            boundary(word.chars().next()),
+            letters.join("\u{00AD}*"),
+            boundary(word.chars().next_back())
+        )
+    }
+}
+
+pub struct AnkiExport;
+
+impl AnkiExport {
+    /// Writes the notes into a tab-separated file that Anki can import directly (File -> Import). The file starts with Anki's header lines, so the separator and the tags column are picked up without any manual setup.
+    /// @param path - the file to write, overwritten if it exists.
+    /// @param notes - the notes to export, one line per note.
+    /// @param fields - which fields every line consists of, in this order.
+    /// @param tags - Anki tags added to every note, written as the last column. No tags column is written if empty.
+    /// @param lang - the language of the sentences, for the language field.
+    /// @returns - a tuple of (number of notes written, number of notes skipped because their cloze field has no deletion, which Anki rejects).
+    pub fn write_tsv(
+        path: &Path,
+        notes: &[AnkiNote],
+        fields: &[AnkiField],
+        tags: &[String],
+        lang: &str,
+    ) -> Result<(usize, usize)> {
+        let file = File::create(path).context(format!(
+            "AnkiExport::write_tsv(): couldn't create the export file `{}`.",
+            path.display()
+        ))?;
+        let mut out = BufWriter::new(file);
+
+        writeln!(out, "#separator:tab")?;
+        writeln!(out, "#html:false")?;
+        if !tags.is_empty() {
+            writeln!(out, "#tags column:{}", fields.len() + 1)?;
+        }
+
+        let joined_tags = tags
+            .iter()
+            .map(|t| t.split_whitespace().collect::<Vec<_>>().join("_"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (mut written, mut skipped) = (0, 0);
+        for note in notes {
+            let cloze = if fields.contains(&AnkiField::Cloze) {
+                let Some(cloze) = note.cloze() else {
+                    skipped += 1;
+                    continue;
+                };
+                cloze
+            } else {
+                String::new()
+            };
+            let mut columns: Vec<String> = fields
+                .iter()
+                .map(|field| match field {
+                    AnkiField::Sentence => note.sentence.raw.clone(),
+                    AnkiField::Target => note.target.clone().unwrap_or_default(),
+                    AnkiField::Cloze => cloze.clone(),
+                    AnkiField::Rating => note.sentence.rating.to_string(),
+                    AnkiField::Language => lang.to_owned(),
+                    AnkiField::Source => note.source.clone(),
+                })
+                .collect();
+            if !tags.is_empty() {
+                columns.push(joined_tags.clone());
+            }
+            let line = columns
+                .iter()
+                .map(|c| Self::escape_field(c))
+                .collect::<Vec<_>>()
+                .join("\t");
+            writeln!(out, "{}", line)?;
+            written += 1;
+        }
+        out.flush()?;
+
+        Ok((written, skipped))
+    }
+
+    /// Anki reads the file like a CSV with tabs: fields with quotes, tabs or newlines have to be quoted, with the quotes inside doubled.
+    fn escape_field(field: &str) -> String {
+        if field.contains(['"', '\t', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloze(raw: &str, target: &str) -> Option<String> {
+        AnkiNote::new(SentenceDoc::new(raw.to_owned(), 0), Some(target.to_owned())).cloze()
+    }
+
+    #[test]
+    fn cloze_matches_the_word_as_it_is_written() {
+        assert_eq!(
+            cloze("I don’t know what they don't.", "don't").as_deref(),
+            Some("I {{c1::don’t}} know what they don't.")
+        );
+        assert_eq!(
+            cloze("Il l’a vu.", "l'").as_deref(),
+            Some("Il {{c1::l’}}a vu.")
+        );
+        assert_eq!(
+            cloze("A well\u{00AD}‐known fact.", "well-known").as_deref(),
+            Some("A {{c1::well\u{00AD}‐known}} fact.")
+        );
+        assert_eq!(
+            cloze("The cat sat on the catalogue.", "cat").as_deref(),
+            Some("The {{c1::cat}} sat on the catalogue.")
+        );
+    }
+
+    #[test]
+    fn cloze_without_the_word_is_none() {
+        assert_eq!(cloze("The dog sat on the catalogue.", "cat"), None);
+        assert_eq!(
+            AnkiNote::new(SentenceDoc::new("No target.".to_owned(), 0), None)
+                .cloze()
+                .as_deref(),
+            Some("No target.")
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result, bail};
+use blake3::Hasher;
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::constants::{COMPRESSED_FILE_EXTENSIONS, DECODABLE_SNIFF_BYTES, STDIN_INPUT};
+use crate::readers::Format;
+
+/// The files and directories a walk has skipped, with why they were skipped.
+pub type Skipped = Vec<(PathBuf, &'static str)>;
+
+/// A single input to train on: a file or the standard input.
+#[derive(Debug, Clone)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    /// Expands the paths given on the command line into the inputs to train on. `-` stands for the standard input, directories are walked recursively.
+    /// @param include - if not empty, only the files in directories matching at least one of these globs are trained on.
+    /// @param exclude - the files in directories matching any of these globs are skipped.
+    /// Globs are matched against the path of a file relative to the directory it was found in (`*` also matches `/`), and don't apply to the files named on the command line directly.
+    /// @returns - the inputs, with the files of every directory sorted by path; and the skipped files and directories found in the walks, with why they were skipped.
+    /// NOTE: a walk skips hidden files and directories, and files that can't be decoded (a stray image or `.DS_Store`), instead of failing the whole run on them. A file named on the command line is always an input, and fails the run if it can't be read.
+    pub fn expand(
+        paths: &[PathBuf],
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<(Vec<Self>, Skipped)> {
+        let include = Self::glob_set(include)?;
+        let exclude = Self::glob_set(exclude)?;
+        let mut inputs = vec![];
+        let mut skipped = vec![];
+
+        for path in paths {
+            if path.is_dir() {
+                let mut walk = WalkDir::new(path)
+                    .follow_links(true)
+                    .sort_by_file_name()
+                    .into_iter();
+                while let Some(entry) = walk.next() {
+                    let entry = entry.context(format!(
+                        "Input::expand(): couldn't walk the directory `{}`.",
+                        path.display()
+                    ))?;
+                    if entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.') {
+                        if entry.file_type().is_dir() {
+                            walk.skip_current_dir();
+                        }
+                        skipped.push((entry.into_path(), "it's hidden"));
+                        continue;
+                    }
+
+                    let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+                    if entry.file_type().is_file()
+                        && (include.is_empty() || include.is_match(relative))
+                        && !exclude.is_match(relative)
+                    {
+                        let file = entry.into_path();
+                        if Self::File(file.clone()).is_decodable() {
+                            inputs.push(Self::File(file));
+                        } else {
+                            skipped.push((file, "it can't be decoded as its format or UTF-8 text"));
+                        }
+                    }
+                }
+            } else if path.as_os_str() == STDIN_INPUT || path.is_file() {
+                inputs.push(Self::from_path(path));
+            } else {
+                bail!(
+                    "Input::expand(): `{}` doesn't exist or is neither a file nor a directory.",
+                    path.display()
+                );
+            }
+        }
+
+        Ok((inputs, skipped))
+    }
+
+    /// A single file, or the standard input for `-`.
+    pub fn from_path(path: &Path) -> Self {
+        if path.as_os_str() == STDIN_INPUT {
+            Self::Stdin
+        } else {
+            Self::File(path.to_path_buf())
+        }
+    }
+
+    /// Opens the input for reading. Compressed input (gzip, zstd or bzip2, recognized by its magic bytes) is decompressed on the fly.
+    /// @returns - a reader that hashes all the content read through it, see `HashingReader`.
+    pub fn open(&self) -> Result<HashingReader<Box<dyn Read>>> {
+        let mut reader: Box<dyn BufRead> = match self {
+            Self::Stdin => Box::new(BufReader::new(io::stdin())),
+            Self::File(path) => Box::new(BufReader::new(File::open(path).context(format!(
+                "Input::open(): couldn't open `{}`. Check if the file exists, then try again.",
+                path.display()
+            ))?)),
+        };
+
+        let magic = reader.fill_buf()?;
+        let reader: Box<dyn Read> = if magic.starts_with(&[0x1f, 0x8b]) {
+            Box::new(MultiGzDecoder::new(reader))
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Box::new(zstd::Decoder::with_buffer(reader)?)
+        } else if magic.starts_with(b"BZh") && magic.get(3).is_some_and(u8::is_ascii_digit) {
+            Box::new(MultiBzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+
+        Ok(HashingReader::new(reader))
+    }
+
+    /// Tells whether the start of the input's (decompressed) content looks like its format: an EPUB is a ZIP archive, a PDF has its header, and everything else is UTF-8 text.
+    /// @returns - false if the input can't be opened or read either.
+    pub fn is_decodable(&self) -> bool {
+        let mut start = vec![];
+        let read = self.open().and_then(|reader| {
+            reader
+                .take(DECODABLE_SNIFF_BYTES)
+                .read_to_end(&mut start)
+                .map_err(anyhow::Error::from)
+        });
+        if read.is_err() {
+            return false;
+        }
+
+        match self.format() {
+            Format::Epub => start.starts_with(b"PK\x03\x04"),
+            Format::Pdf => start.windows(5).any(|window| window == b"%PDF-"),
+            // NOTE: the sniffed bytes may end in the middle of a character, which doesn't make the text undecodable.
+            _ => match std::str::from_utf8(&start) {
+                Ok(_) => true,
+                Err(e) => e.error_len().is_none(),
+            },
+        }
+    }
+
+    /// The format of the input's (decompressed) content, detected from the file extension that comes before the compression one: `movie.srt.gz` holds subtitles. The standard input is always plain text.
+    pub fn format(&self) -> Format {
+        match self {
+            Self::Stdin => Format::Text,
+            Self::File(path) => {
+                let name = path.to_string_lossy();
+                let name = COMPRESSED_FILE_EXTENSIONS
+                    .iter()
+                    .find_map(|extension| name.strip_suffix(extension))
+                    .unwrap_or(&name);
+                Format::from_path(name)
+            }
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Stdin => None,
+            Self::File(path) => Some(path),
+        }
+    }
+
+    /// The size and the modification time (nanoseconds since the Unix epoch) of a file, to tell whether it has changed since it was trained on without reading it.
+    /// @returns - None for the standard input, or if the file system doesn't record modification times.
+    pub fn stamp(&self) -> Option<(u64, u64)> {
+        let Self::File(path) = self else {
+            return None;
+        };
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        Some((metadata.len(), modified.as_nanos() as u64))
+    }
+
+    /// The path recorded for the input in its source doc: the absolute path of a file, or `-` for the standard input.
+    pub fn source_path(&self) -> String {
+        match self {
+            Self::Stdin => STDIN_INPUT.to_owned(),
+            Self::File(path) => std::fs::canonicalize(path)
+                .unwrap_or(path.clone())
+                .display()
+                .to_string(),
+        }
+    }
+
+    fn glob_set(globs: &[String]) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(Glob::new(glob).context(format!(
+                "Input::glob_set(): `{}` is not a valid glob.",
+                glob
+            ))?);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdin => write!(f, "<stdin>"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Hashes everything that is read through it with blake3, so that the content of an input can be hashed while it's being read, even when it can only be read once (the standard input).
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::new(),
+        }
+    }
+
+    /// Reads whatever is left of the content (readers may stop before the end, e.g. after the closing tag of an XML document) and returns the hash of all of it.
+    pub fn finalize(mut self) -> Result<[u8; 32]> {
+        io::copy(&mut self, &mut io::sink())?;
+
+        Ok(*self.hasher.finalize().as_bytes())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::RawData;
+    use crate::cli::Preset;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn temp_dir(test: &str) -> Result<PathBuf> {
+        let dir =
+            std::env::temp_dir().join(format!("freq-sage-test-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(dir)
+    }
+
+    fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content)?;
+
+        Ok(encoder.finish()?)
+    }
+
+    #[test]
+    fn walks_skip_hidden_and_undecodable_files() -> Result<()> {
+        let dir = temp_dir("walk")?;
+        std::fs::create_dir_all(dir.join(".git"))?;
+        std::fs::write(dir.join(".git/config"), "[core]")?;
+        std::fs::write(dir.join(".DS_Store"), [0, 0, 0, 1, b'B', b'u', 0xff, 0xfe])?;
+        std::fs::write(dir.join("a.txt"), "Il était une fois.")?;
+        std::fs::write(dir.join("b.txt.gz"), gzip("Une histoire.".as_bytes())?)?;
+        std::fs::write(
+            dir.join("cover.png"),
+            [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff],
+        )?;
+        std::fs::write(dir.join("fake.epub"), "not a zip")?;
+
+        let (inputs, skipped) = Input::expand(std::slice::from_ref(&dir), &[], &[])?;
+        let names = |paths: Vec<&Path>| -> Vec<String> {
+            paths
+                .into_iter()
+                .map(|path| path.strip_prefix(&dir).unwrap().display().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(inputs.iter().filter_map(Input::path).collect()),
+            ["a.txt", "b.txt.gz"]
+        );
+        assert_eq!(
+            names(skipped.iter().map(|(path, _)| path.as_path()).collect()),
+            [".DS_Store", ".git", "cover.png", "fake.epub"]
+        );
+
+        // A file named on the command line is never skipped, reading it fails the run instead.
+        let (inputs, skipped) = Input::expand(&[dir.join("cover.png")], &[], &[])?;
+        assert_eq!(inputs.len(), 1);
+        assert!(skipped.is_empty());
+        assert!(RawData::from_input(&inputs[0], "en".to_owned(), Preset::Plain).is_err());
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn hashes_the_decompressed_content() -> Result<()> {
+        let dir = temp_dir("hashing")?;
+        let text = "The cat sat on the mat. The dog sat on the rug.";
+        let path = dir.join("pets.txt.gz");
+        std::fs::write(&path, gzip(text.as_bytes())?)?;
+
+        // Whatever is left unread is still hashed.
+        let mut reader = Input::File(path).open()?;
+        let mut start = [0; 8];
+        reader.read_exact(&mut start)?;
+        assert_eq!(&start, b"The cat ");
+        assert_eq!(
+            reader.finalize()?,
+            *blake3::hash(text.as_bytes()).as_bytes()
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
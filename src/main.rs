@@ -2,94 +2,663 @@ pub mod analysis;
 pub mod cli;
 pub mod constants;
 pub mod database;
+pub mod export;
+pub mod input;
+pub mod presets;
+pub mod readers;
+pub mod segmenter;
+pub mod tokenizer;
 pub mod util;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::Parser;
 use dotenvy::dotenv;
+use regex::Regex;
 
-use crate::analysis::RawData;
-use crate::cli::CLI;
-use crate::constants::{DEFAULT_LANGUAGE, DEFAULT_TOP_N_LIMIT};
-use crate::database::SageDatabase;
-
-// TODO: [AFTER DB] Apart from the ability to train the database, Sage must also have the ability to dry-run and just show the rankings of sentences in this specific text, without adding the info to the DB. (training and dry-running should potentially be two different subcommands?)
-// TODO: [POTENTIALLY] implement support of several file formats so (for example) processing PDF books becomes possible.
-// TODO: Implement the ability to clean up the database based on a regex filter (remove all sentences that match the filter from the database).
+use crate::analysis::{RawData, SentenceRanker};
+use crate::cli::{CLI, Preset};
+use crate::constants::{DEFAULT_LANGUAGE, DEFAULT_TOP_N_LIMIT, TRAINING_BATCH_SENTENCES};
+use crate::database::{SageDatabase, SourceDoc, TrainingRun, ValueBounds};
+use crate::export::{AnkiExport, AnkiNote};
+use crate::input::Input;
+use crate::tokenizer::Tokenizer;
+use crate::util::Util;
 
 fn main() -> Result<()> {
     dotenv().ok();
 
-    let dlang = std::env::var("DEFAULT_LANGUAGE").unwrap_or(DEFAULT_LANGUAGE.to_owned());
-    // TODO: Introduce a CLI argument to force a custom database language, something other than env var or the default.
-    let mut db = SageDatabase::new(&dlang)?;
-
     let conf_limit = std::env::var("DEFAULT_TOP_N_LIMIT")
         .ok()
         .and_then(|l| l.parse::<u32>().ok())
         .unwrap_or(DEFAULT_TOP_N_LIMIT);
 
     let cli = CLI::parse();
+
+    // NOTE: the CLI language wins over the env var, which wins over the default.
+    let dlang = cli.lang.unwrap_or_else(|| {
+        std::env::var("DEFAULT_LANGUAGE").unwrap_or(DEFAULT_LANGUAGE.to_owned())
+    });
+    let db_path = match cli.db {
+        Some(path) => path,
+        None => SageDatabase::default_path(&dlang)?,
+    };
+    // NOTE: the DB is only opened by the commands that need it, so that dry runs like `analyze` never create or write into it.
+    let open_db = || SageDatabase::new(&db_path, &dlang, cli.create);
+
     match cli.command {
-        cli::Commands::Status { language } => {
-            let status_db = match language {
-                None => db,
-                Some(l) => {
-                    if l == dlang {
-                        db
-                    } else {
-                        SageDatabase::new(&l)?
-                    }
-                }
-            };
+        cli::Commands::Status => {
+            let status_db = open_db()?;
             match SageDatabase::status_check(&status_db) {
                 Err(e) => println!("[!!!] {}", e),
                 Ok((freq_len, sents_len)) => println!(
-                    "[STATUS] `{}.redb` database status OK, the DB has {} frequencies and {} sentence rankings.",
-                    &status_db.lang, freq_len, sents_len
+                    "[STATUS] `{}` ({}) database status OK, the DB has {} frequencies and {} sentence rankings.",
+                    status_db.path.display(),
+                    &status_db.lang,
+                    freq_len,
+                    sents_len
                 ),
             }
         }
-        // TODO: It should be possible to explicitly set the language of the file you're training with in the CLI parameters of train mode.
-        cli::Commands::Train { file } => {
-            let data = RawData::from_file(file.to_str().unwrap(), dlang)?;
-
-            db.insert_freqs(&data)
-                .context("main(): while trying to insert new freqs into the database.")?;
-            let sizes = data.data_sizes();
-            let new_freqs = db
-                .freqs_of_words(&data.freqs.keys().cloned().collect::<Vec<String>>())
-                .context("main(): while trying to re-retrieve new values of frequencies.")?;
-
-            db.insert_rankings(new_freqs, data.sentences)
-                .context("main(): while trying to insert rankings into the database.")?;
+        cli::Commands::List => {
+            let data_dir = SageDatabase::data_dir()?;
+            let found = SageDatabase::list_databases(&data_dir)?;
+            println!(
+                "[LIST] {} language databases in `{}`:",
+                found.len(),
+                data_dir.display()
+            );
+            for (lang, path) in found {
+                match SageDatabase::peek_status(&path) {
+                    Ok((freq_len, sents_len)) => println!(
+                        "- {}: {} frequencies and {} sentence rankings.",
+                        lang, freq_len, sents_len
+                    ),
+                    Err(e) => println!("- {}: [!!!] couldn't be read: {}", lang, e),
+                }
+            }
+        }
+        cli::Commands::Train {
+            inputs,
+            include,
+            exclude,
+            preset,
+            force,
+        } => {
+            let (inputs, walk_skipped) = Input::expand(&inputs, &include, &exclude)?;
+            for (path, reason) in &walk_skipped {
+                println!("[!!!] SKIPPED: `{}`, {}.", path.display(), reason);
+            }
+            if inputs.is_empty() {
+                println!(
+                    "[!!!] Nothing to train on: no files were found, check the `--include`/`--exclude` globs."
+                );
+                return Ok(());
+            }
+
+            let mut db = open_db()?;
+            let mut run = db.begin_training()?;
+            let (mut trained, mut skipped) = (0, 0);
+            let (mut freq_total, mut sentence_total) = (0, 0);
+            for input in &inputs {
+                let trained_source =
+                    train_input(&mut run, input, &dlang, preset, force).context(format!(
+                        "main(): while trying to train on `{}`, nothing has been trained on.",
+                        input
+                    ))?;
+                let Some((id, source)) = trained_source else {
+                    skipped += 1;
+                    continue;
+                };
+
+                println!(
+                    "- `{}` (source {}): {} frequencies and {} sentences.",
+                    input, id, source.word_count, source.sentence_count
+                );
+                trained += 1;
+                freq_total += source.word_count;
+                sentence_total += source.sentence_count;
+            }
+            // NOTE: a run that skipped every input is only the training and the taking back of duplicates, there's nothing to commit.
+            if trained == 0 {
+                run.abort()?;
+                println!(
+                    "SUCCESS: nothing new to train on, all {} inputs have been skipped.",
+                    inputs.len()
+                );
+                return Ok(());
+            }
+            let rescored = run.commit().context(
+                "main(): while trying to rescore the stored sentence rankings and commit the training run.",
+            )?;
+            println!(
+                "SUCCESS: trained on {} of {} inputs ({} skipped) containing {} frequencies and {} sentences in total, rescored {} stored sentences.",
+                trained,
+                inputs.len(),
+                skipped,
+                freq_total,
+                sentence_total,
+                rescored
+            );
+        }
+        cli::Commands::Sources => {
+            let db = open_db()?;
+            let sources = db.sources()?;
+            println!(
+                "[SOURCES] `{}` has been trained on {} files:",
+                db.path.display(),
+                sources.len()
+            );
+            for (id, source) in sources {
+                println!(
+                    "{}. `{}` [{}] trained at {}: {} words and {} sentences, blake3 {}",
+                    id,
+                    source.path,
+                    source.lang,
+                    Util::format_timestamp(source.trained_at),
+                    source.word_count,
+                    source.sentence_count,
+                    blake3::Hash::from_bytes(source.content_hash).to_hex()
+                );
+            }
+        }
+        cli::Commands::Untrain { source } => {
+            let mut db = open_db()?;
+            let sources = db.sources()?;
+            let by_id = source
+                .parse::<u64>()
+                .ok()
+                .filter(|id| sources.iter().any(|(s, _)| s == id));
+            let id = match by_id {
+                Some(id) => id,
+                None => {
+                    let path = std::fs::canonicalize(&source)
+                        .map(|p| p.display().to_string())
+                        .unwrap_or(source.clone());
+                    sources
+                        .iter()
+                        .rev()
+                        .find(|(_, s)| s.path == path)
+                        .map(|(id, _)| *id)
+                        .context(format!(
+                            "main(): `{}` is neither the id nor the path of a trained source, see the `sources` command for the trained sources.",
+                            source
+                        ))?
+                }
+            };
+
+            let (changed_words, removed_words, removed_sentences, rescored) = db
+                .untrain(id)
+                .context("main(): while trying to untrain the source.")?;
+            println!(
+                "SUCCESS: untrained source {}: {} word frequencies decreased, {} words and {} sentences removed, rescored {} stored sentences.",
+                id,
+                changed_words.len(),
+                removed_words,
+                removed_sentences,
+                rescored
+            );
+        }
+        cli::Commands::Analyze {
+            file,
+            preset,
+            against_db,
+            limit,
+            no_limit,
+        } => {
+            let effective_limit = effective_limit(limit, no_limit, conf_limit)
+                .map(|l| l as usize)
+                .unwrap_or(usize::MAX);
+            let (mut data, _) =
+                RawData::from_input(&Input::from_path(&file), dlang.clone(), preset)?;
+            let (freq_count, sentence_count) = data.data_sizes();
+
+            let mut text_freqs: Vec<(&String, &u64)> = data.freqs.iter().collect();
+            text_freqs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
             println!(
-                "SUCCESS: processed file `{}` containing {} frequencies and {} sentences.",
+                "[ANALYSIS] `{}` contains {} unique words and {} sentences.",
                 file.display(),
-                sizes.0,
-                sizes.1
+                freq_count,
+                sentence_count
             );
+            println!("[TOP FREQUENCIES IN THE TEXT]");
+            for (index, (word, freq)) in text_freqs.iter().take(effective_limit).enumerate() {
+                println!("{}. `{}`: {}", index + 1, word, freq)
+            }
+
+            if against_db {
+                let words = data.freqs.keys().cloned().collect::<Vec<String>>();
+                let db_freqs = SageDatabase::peek_freqs(&db_path, &words)
+                    .context("main(): while trying to look up the text's words in the database.")?;
+                let total: u64 = data.freqs.values().sum();
+                let covered: u64 = data
+                    .freqs
+                    .iter()
+                    .filter(|(w, _)| db_freqs[*w] != 0)
+                    .map(|(_, f)| f)
+                    .sum();
+                let known_unique = db_freqs.values().filter(|f| **f != 0).count();
+                println!(
+                    "[COVERAGE] {:.1}% of the word occurrences in the text ({} of {} unique words) are already in the `{}` database.",
+                    covered as f64 * 100.0 / total.max(1) as f64,
+                    known_unique,
+                    freq_count,
+                    db_path.display()
+                );
+                data = RawData::from_preexisting_data(db_freqs, data.sentences, data.lang);
+            }
+
+            let ranker = SentenceRanker::new(&data);
+            println!(
+                "[TOP RANKINGS IN THE TEXT ({})]",
+                if against_db {
+                    "scored against the database frequencies"
+                } else {
+                    "scored against the text's own frequencies"
+                }
+            );
+            // NOTE: the ranker sorts from the hardest to the easiest, the easiest are shown first here.
+            for (index, rank) in ranker
+                .rankings()
+                .iter()
+                .rev()
+                .take(effective_limit)
+                .enumerate()
+            {
+                println!(
+                    "{}. `{}` [{}]: {}",
+                    index + 1,
+                    rank.sentence,
+                    dlang,
+                    rank.score
+                )
+            }
         }
         cli::Commands::Show {
             what,
             limit,
             no_limit,
+            offset,
+            from_rank,
+            at_most,
+            at_least,
+            sources,
         } => {
-            let effective_limit = (!no_limit).then_some(limit.or(Some(conf_limit))).flatten();
+            let mut db = open_db()?;
+            let effective_limit = effective_limit(limit, no_limit, conf_limit);
+            let bounds = ValueBounds::new(at_most, at_least);
+            if bounds.is_empty() {
+                bail!(
+                    "main(): `--at-least` can't be bigger than `--at-most`, nothing would be shown."
+                );
+            }
+            // NOTE: ranks start from 1, so starting from rank N means skipping N - 1 entries.
+            let offset = from_rank.map(|r| r.saturating_sub(1)).unwrap_or(offset);
             match what {
                 cli::ShowType::Frequencies => {
-                    for (index, freq) in db.top_freqs(effective_limit)?.iter().enumerate() {
-                        println!("{}. `{}`: {}", index + 1, freq.word, freq.freq)
+                    for (rank, freq) in db.top_freqs(bounds, offset, effective_limit)? {
+                        println!("{}. `{}`: {}", rank, freq.word, freq.freq)
                     }
                 }
                 cli::ShowType::Rankings => {
-                    for (index, rank) in db.top_rankings(effective_limit)?.iter().enumerate() {
-                        println!("{}. `{}` [{}]: {}", index + 1, rank.raw, dlang, rank.rating)
+                    for (rank, sentence) in db.top_rankings(bounds, offset, effective_limit)? {
+                        println!(
+                            "{}. `{}` [{}]: {}",
+                            rank, sentence.raw, dlang, sentence.rating
+                        );
+                        if sources {
+                            print_sources(&db, &sentence.raw)?;
+                        }
+                    }
+                }
+                cli::ShowType::IPlusOne => {
+                    for (index, (rank, word)) in db
+                        .i_plus_one(bounds, offset, effective_limit)?
+                        .iter()
+                        .enumerate()
+                    {
+                        println!(
+                            "{}. `{}` [{}]: {} (unknown: `{}`)",
+                            offset + index as u64 + 1,
+                            rank.raw,
+                            dlang,
+                            rank.rating,
+                            word
+                        );
+                        if sources {
+                            print_sources(&db, &rank.raw)?;
+                        }
                     }
                 }
             }
         }
+        cli::Commands::Mark {
+            status,
+            mut words,
+            file,
+        } => {
+            let mut db = open_db()?;
+            if let Some(f) = &file {
+                let text = std::fs::read_to_string(f).context(format!(
+                    "main(): couldn't read the words to mark from `{}`.",
+                    f.display()
+                ))?;
+                words.extend(text.split_whitespace().map(str::to_owned));
+            }
+            let tokenizer = Tokenizer::new(&dlang);
+            let words: Vec<String> = words.iter().flat_map(|w| tokenizer.words(w)).collect();
+
+            let marked = match status {
+                cli::WordStatus::Known => db.mark_known(&words, false)?,
+                cli::WordStatus::Ignored => db.mark_known(&words, true)?,
+                cli::WordStatus::Unknown => db.mark_unknown(&words)?,
+            };
+            println!("SUCCESS: marked {} words as {:?}.", marked, status);
+        }
+        cli::Commands::Export {
+            format,
+            output,
+            word,
+            i_plus_one,
+            fields,
+            tags,
+            limit,
+            no_limit,
+        } => {
+            let mut db = open_db()?;
+            let effective_limit = effective_limit(limit, no_limit, conf_limit);
+            let mut notes: Vec<AnkiNote> = if let Some(w) = word {
+                let target = single_word(&w, &dlang)?;
+                db.sentences_with_word(&target, effective_limit)?
+                    .into_iter()
+                    .map(|s| AnkiNote::new(s, Some(target.clone())))
+                    .collect()
+            } else if i_plus_one {
+                db.i_plus_one(ValueBounds::default(), 0, effective_limit)?
+                    .into_iter()
+                    .map(|(s, target)| AnkiNote::new(s, Some(target)))
+                    .collect()
+            } else {
+                db.top_rankings(ValueBounds::default(), 0, effective_limit)?
+                    .into_iter()
+                    .map(|(_, s)| AnkiNote::new(s, None))
+                    .collect()
+            };
+
+            if fields.contains(&cli::AnkiField::Source) {
+                for note in &mut notes {
+                    note.source = db
+                        .sources_of_sentence(&note.sentence.raw)?
+                        .into_iter()
+                        .map(|(_, source, location)| {
+                            if location.is_empty() {
+                                source.path
+                            } else {
+                                format!("{} ({})", source.path, location)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("; ");
+                }
+            }
+
+            let (written, skipped) = match format {
+                cli::ExportFormat::Anki => {
+                    AnkiExport::write_tsv(&output, &notes, &fields, &tags, &dlang)?
+                }
+            };
+            if skipped > 0 {
+                println!(
+                    "[!!!] skipped {} sentences whose target word couldn't be found in them, their cloze field would have no deletion.",
+                    skipped
+                );
+            }
+            println!(
+                "SUCCESS: exported {} sentences into `{}`.",
+                written,
+                output.display()
+            );
+        }
+        cli::Commands::Random {
+            count,
+            min_easiness,
+            unseen,
+            forget_seen,
+            batch,
+        } => {
+            let mut db = open_db()?;
+            if forget_seen {
+                let forgotten = db.forget_seen()?;
+                println!("Forgot {} previously shown sentences.", forgotten);
+            }
+            let drawn =
+                db.random_batch(&batch, count.unwrap_or(conf_limit), min_easiness, unseen)?;
+            for (index, sentence) in drawn.iter().enumerate() {
+                println!(
+                    "{}. `{}` [{}]: {}",
+                    index + 1,
+                    sentence.raw,
+                    dlang,
+                    sentence.rating
+                )
+            }
+        }
+        cli::Commands::Batch { name, regen, save } => {
+            let mut db = open_db()?;
+            let sentences = if regen.is_empty() {
+                db.batch(&name)?.context(format!(
+                    "main(): there's no batch called `{}`. Draw it with the `random` command first.",
+                    name
+                ))?
+            } else {
+                let (sentences, replaced) = db.regen_batch(&name, &regen)?;
+                if replaced < regen.len() {
+                    println!(
+                        "[!!!] only {} of the requested sentences could be replaced, there are no more sentences left to draw.",
+                        replaced
+                    );
+                }
+                sentences
+            };
+
+            for (index, sentence) in sentences.iter().enumerate() {
+                println!(
+                    "{}. `{}` [{}]: {}",
+                    index + 1,
+                    sentence.raw,
+                    dlang,
+                    sentence.rating
+                )
+            }
+            if let Some(path) = save {
+                let lines: String = sentences.iter().map(|s| format!("{}\n", s.raw)).collect();
+                std::fs::write(&path, lines).context(format!(
+                    "main(): couldn't write the batch into `{}`.",
+                    path.display()
+                ))?;
+                println!(
+                    "SUCCESS: saved {} sentences of batch `{}` into `{}`.",
+                    sentences.len(),
+                    name,
+                    path.display()
+                );
+            }
+        }
+        cli::Commands::Clean {
+            pattern,
+            words,
+            dry_run,
+        } => {
+            let mut db = open_db()?;
+            let regex = Regex::new(&pattern).context(format!(
+                "main(): `{}` is not a valid regex for the cleanup.",
+                pattern
+            ))?;
+            let (removed_sentences, removed_words, rescored) = db.clean(&regex, words, dry_run)?;
+
+            let verb = if dry_run { "would remove" } else { "removed" };
+            for sentence in &removed_sentences {
+                println!("- sentence `{}`", sentence);
+            }
+            for word in &removed_words {
+                println!("- word `{}`", word);
+            }
+            println!(
+                "{}: {} {} sentences and {} words matching `{}`{}.",
+                if dry_run { "[DRY RUN]" } else { "SUCCESS" },
+                verb,
+                removed_sentences.len(),
+                removed_words.len(),
+                pattern,
+                if rescored > 0 {
+                    format!(", rescored {} stored sentences", rescored)
+                } else {
+                    String::new()
+                }
+            );
+        }
+        cli::Commands::Search {
+            how,
+            query,
+            limit,
+            no_limit,
+        } => {
+            let mut db = open_db()?;
+            let effective_limit = effective_limit(limit, no_limit, conf_limit);
+            let found = match how {
+                cli::SearchType::Word => {
+                    let word = single_word(&query, &dlang)?;
+                    db.sentences_with_word(&word, effective_limit)?
+                }
+                cli::SearchType::Phrase => db.sentences_with_phrase(
+                    &Tokenizer::new(&dlang).words(&query),
+                    effective_limit,
+                )?,
+                cli::SearchType::Regex => {
+                    let pattern = Regex::new(&query).context(format!(
+                        "main(): `{}` is not a valid regex for the search.",
+                        query
+                    ))?;
+                    db.sentences_matching(&pattern, effective_limit)?
+                }
+            };
+            if found.is_empty() {
+                println!("No sentences found for `{}`.", query);
+            }
+            for (index, rank) in found.iter().enumerate() {
+                println!("{}. `{}` [{}]: {}", index + 1, rank.raw, dlang, rank.rating)
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Trains on a single input, flushing its data to the run in batches of `TRAINING_BATCH_SENTENCES` sentences as it's read.
+/// @returns - the id and the source doc of the input, None if it has been skipped because its content has already been trained on.
+fn train_input(
+    run: &mut TrainingRun,
+    input: &Input,
+    lang: &str,
+    preset: Preset,
+    force: bool,
+) -> Result<Option<(u64, SourceDoc)>> {
+    let path = input.source_path();
+    let stamp = input.stamp();
+    // NOTE: a file that hasn't changed since it was trained on is skipped without reading it.
+    let unchanged = match stamp {
+        Some(stamp) => run.source_by_stamp(&path, stamp)?,
+        None => None,
+    };
+    let checked = unchanged.is_some();
+    if skip_duplicate(input, unchanged, force) {
+        return Ok(None);
+    }
+
+    let id = run.begin_source(path)?;
+    let mut batch = RawData::new(lang.to_owned());
+    let mut sentence_count: u64 = 0;
+    let content_hash = RawData::read_input(input, lang, preset, &mut |sentence, location| {
+        batch.push(sentence, location);
+        if batch.sentences.len() >= TRAINING_BATCH_SENTENCES {
+            run.train(id, &batch)?;
+            sentence_count += batch.sentences.len() as u64;
+            batch = RawData::new(lang.to_owned());
+        }
+        Ok(())
+    })?;
+    run.train(id, &batch)?;
+    sentence_count += batch.sentences.len() as u64;
+
+    // NOTE: the same content under another path (a copy, or the standard input) is only found once it's been hashed while training on it, and is taken back before the run is committed. Reading every input twice just to skip the rare duplicate up front would double the slowest part of training, and the standard input can only be read once anyway.
+    if !checked && skip_duplicate(input, run.source_by_content(&content_hash)?, force) {
+        run.discard_source(id)?;
+        return Ok(None);
     }
 
+    Ok(Some((
+        id,
+        run.finish_source(id, content_hash, sentence_count, stamp)?,
+    )))
+}
+
+/// Tells the user about an input whose content has already been trained on.
+/// @param duplicate - the source with the same content as the input, if there is one.
+/// @returns - true if the input has to be skipped, false if it's new or `--force` is given.
+fn skip_duplicate(input: &Input, duplicate: Option<(u64, SourceDoc)>, force: bool) -> bool {
+    let Some((id, source)) = duplicate else {
+        return false;
+    };
+
+    if force {
+        println!(
+            "[!!!] `{}` has the same content as source {} (`{}`, trained at {}), training on it again because of `--force`.",
+            input,
+            id,
+            source.path,
+            Util::format_timestamp(source.trained_at)
+        );
+        false
+    } else {
+        println!(
+            "[!!!] SKIPPED: `{}` has the same content as source {} (`{}`, trained at {}). Training on it again would double-count all of its words. Use `--force` if that's really what you want.",
+            input,
+            id,
+            source.path,
+            Util::format_timestamp(source.trained_at)
+        );
+        true
+    }
+}
+
+/// Resolves the limit for the commands that print top N of something: the CLI `--limit` wins over the env/default one, and `--no-limit` means no limit at all.
+fn effective_limit(limit: Option<u32>, no_limit: bool, conf_limit: u32) -> Option<u32> {
+    (!no_limit).then_some(limit.or(Some(conf_limit))).flatten()
+}
+
+/// Splits a word given on the command line the way the sentences are split, see `Tokenizer::words()`.
+/// @returns - the word, if the query is exactly one.
+fn single_word(query: &str, lang: &str) -> Result<String> {
+    let mut words = Tokenizer::new(lang).words(query);
+    if words.len() != 1 {
+        bail!(
+            "main(): `{}` is {} words for {}, not a single one. Look it up with `search phrase` instead.",
+            query,
+            words.len(),
+            lang
+        );
+    }
+
+    Ok(words.remove(0))
+}
+
+/// Prints the sources a sentence came from under the sentence itself.
+fn print_sources(db: &SageDatabase, raw: &str) -> Result<()> {
+    for (id, source, location) in db.sources_of_sentence(raw)? {
+        if location.is_empty() {
+            println!("    from source {}: `{}`", id, source.path);
+        } else {
+            println!("    from source {}: `{}` at {}", id, source.path, location);
+        }
+    }
     Ok(())
 }
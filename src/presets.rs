@@ -0,0 +1,280 @@
+use std::io::BufRead;
+
+use anyhow::{Context, Result};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use scraper::{Html, Selector};
+
+use crate::cli::Preset;
+use crate::constants::{
+    GUTENBERG_CONTENTS_MAX_LINE_LEN, GUTENBERG_CONTENTS_PATTERN, GUTENBERG_END_PATTERN,
+    GUTENBERG_NOTE_PATTERN, GUTENBERG_START_PATTERN, HTML_ENTITIES, WIKI_DROPPED_LINE_PREFIXES,
+    WIKI_DROPPED_MARKUP_PATTERN, WIKI_EXTERNAL_LINK_PATTERN, WIKI_FORMATTING_PATTERN,
+    WIKI_HTML_DOCUMENT_PATTERN, WIKI_HTML_DROPPED_CLASSES, WIKI_HTML_DROPPED_TAGS,
+};
+use crate::readers::Readers;
+
+/// Presets clean up the text of specific sources before it's split into sentences, dropping everything that isn't a part of the text itself (licenses, navigation etc.).
+pub struct Presets;
+
+impl Presets {
+    pub fn apply(preset: Preset, text: String) -> String {
+        match preset {
+            Preset::Plain => text,
+            Preset::Gutenberg => Self::gutenberg(&text),
+            Preset::Wikipedia if WIKI_HTML_DOCUMENT_PATTERN.is_match(&text) => {
+                Self::wikipedia_html(&text)
+            }
+            Preset::Wikipedia => Self::wikitext(&text),
+        }
+    }
+
+    /// Cleans up a Project Gutenberg plain text book:
+    /// - only keeps the text between the `*** START OF ... ***` and `*** END OF ... ***` markers (the whole text if a marker is missing), which drops the license and the header;
+    /// - drops the production credits, transcriber's notes and illustration placeholders;
+    /// - drops the table of contents and the all-caps headings (CHAPTER I etc.);
+    /// - rejoins the hard-wrapped lines of every paragraph into a single line.
+    pub fn gutenberg(text: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        let start = GUTENBERG_START_PATTERN
+            .find(&text)
+            .map(|m| m.end())
+            .unwrap_or(0);
+        let end = GUTENBERG_END_PATTERN
+            .find_at(&text, start)
+            .map(|m| m.start())
+            .unwrap_or(text.len());
+
+        let mut paragraphs = vec![];
+        let mut in_contents = false;
+        for paragraph in text[start..end].split("\n\n") {
+            let lines: Vec<&str> = paragraph
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect();
+            if lines.is_empty() {
+                continue;
+            }
+
+            if GUTENBERG_CONTENTS_PATTERN.is_match(lines[0]) {
+                in_contents = true;
+                continue;
+            }
+            // NOTE: the table of contents ends with the first paragraph that has a line as long as prose.
+            if in_contents {
+                if lines
+                    .iter()
+                    .all(|l| l.chars().count() <= GUTENBERG_CONTENTS_MAX_LINE_LEN)
+                {
+                    continue;
+                }
+                in_contents = false;
+            }
+
+            if GUTENBERG_NOTE_PATTERN.is_match(lines[0]) {
+                continue;
+            }
+            if lines.len() == 1 && !lines[0].chars().any(char::is_lowercase) {
+                continue;
+            }
+
+            paragraphs.push(lines.join(" "));
+        }
+
+        paragraphs.join("\n\n")
+    }
+
+    /// Streams a Wikipedia `pages-articles.xml` dump page by page, calling `on_article` with the cleaned up text of every article, and stopping at the first error it returns. Pages outside of the main namespace (talk pages, templates etc.) and redirects are skipped. Only a single page is ever held in memory.
+    /// @returns - the number of processed articles.
+    pub fn wikipedia_dump<R: BufRead>(
+        reader: R,
+        mut on_article: impl FnMut(String) -> Result<()>,
+    ) -> Result<usize> {
+        let mut reader = Reader::from_reader(reader);
+        let mut buf = vec![];
+        let (mut current_tag, mut namespace, mut wikitext) = (vec![], String::new(), String::new());
+        let mut redirect = false;
+        let mut articles = 0;
+
+        loop {
+            let event = reader.read_event_into(&mut buf).context(format!(
+                "Presets::wikipedia_dump(): malformed XML at byte {}, is this a Wikipedia dump?",
+                reader.buffer_position()
+            ))?;
+            match event {
+                Event::Start(e) => {
+                    current_tag = e.local_name().as_ref().to_vec();
+                    match current_tag.as_slice() {
+                        b"page" => {
+                            namespace.clear();
+                            wikitext.clear();
+                            redirect = false;
+                        }
+                        b"redirect" => redirect = true,
+                        _ => {}
+                    }
+                }
+                Event::Empty(e) if e.local_name().as_ref() == b"redirect" => redirect = true,
+                Event::Text(e) => {
+                    let text = e.unescape()?;
+                    match current_tag.as_slice() {
+                        b"ns" => namespace.push_str(&text),
+                        b"text" => wikitext.push_str(&text),
+                        _ => {}
+                    }
+                }
+                Event::End(e) => {
+                    current_tag.clear();
+                    if e.local_name().as_ref() == b"page" && namespace.trim() == "0" && !redirect {
+                        on_article(Self::wikitext(&wikitext))?;
+                        articles += 1;
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(articles)
+    }
+
+    /// Turns the wikitext of a single article into plain prose:
+    /// - comments, references and the tags that never contain prose (formulas, galleries, code) are dropped;
+    /// - templates (infoboxes included) and tables are dropped, even when nested in each other;
+    /// - links are replaced with their labels, links to files, categories and other namespaces are dropped;
+    /// - headings, lists and indented lines are dropped, as they're rarely full sentences;
+    /// - paragraphs are separated by blank lines.
+    pub fn wikitext(text: &str) -> String {
+        let text = WIKI_DROPPED_MARKUP_PATTERN.replace_all(text, "");
+        let text = Self::replace_nested(&text, &[("{{", "}}"), ("{|", "|}")], |_| String::new());
+        let text = Self::replace_nested(&text, &[("[[", "]]")], |link| {
+            let (target, label) = link.split_once('|').unwrap_or((link, link));
+            if target.contains(':') {
+                String::new()
+            } else {
+                label.to_owned()
+            }
+        });
+        let text = WIKI_EXTERNAL_LINK_PATTERN.replace_all(&text, "$1");
+        let text = WIKI_FORMATTING_PATTERN.replace_all(&text, "");
+
+        // NOTE: blank and dropped lines end a paragraph, so that a line without final punctuation (a leftover caption) never continues into the next paragraph's first sentence.
+        let text = Self::decode_entities(&text);
+        let mut paragraphs: Vec<Vec<&str>> = vec![vec![]];
+        for line in text.lines().map(str::trim) {
+            let paragraph = paragraphs.last_mut().expect("never empty");
+            if !line.is_empty() && !line.starts_with(WIKI_DROPPED_LINE_PREFIXES) {
+                paragraph.push(line);
+            } else if !paragraph.is_empty() {
+                paragraphs.push(vec![]);
+            }
+        }
+
+        paragraphs
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| p.join("\n"))
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+
+    /// Extracts the prose of a saved Wikipedia article page, skipping the site navigation, infoboxes, tables, references, headings and lists.
+    pub fn wikipedia_html(text: &str) -> String {
+        let document = Html::parse_document(text);
+        let content = Selector::parse("#mw-content-text").expect("valid selector");
+        let root = document
+            .select(&content)
+            .next()
+            .unwrap_or(document.root_element());
+
+        Readers::html_blocks(root, &|e| {
+            WIKI_HTML_DROPPED_TAGS.contains(&e.name())
+                || e.classes().any(|c| WIKI_HTML_DROPPED_CLASSES.contains(&c))
+        })
+        .join("\n\n")
+    }
+
+    /// Replaces every outermost block delimited by one of the `delimiters` pairs with the result of `replace`, called with the block's inner text. Blocks of any of the pairs can be nested in each other. An unclosed block swallows the rest of the text.
+    fn replace_nested(
+        text: &str,
+        delimiters: &[(&str, &str)],
+        mut replace: impl FnMut(&str) -> String,
+    ) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut closing_stack: Vec<&str> = vec![];
+        let (mut kept_from, mut block_from) = (0, 0);
+        let mut i = 0;
+
+        while i < text.len() {
+            let rest = &text[i..];
+            if let Some((open, close)) = delimiters.iter().find(|(open, _)| rest.starts_with(open))
+            {
+                if closing_stack.is_empty() {
+                    out.push_str(&text[kept_from..i]);
+                    block_from = i + open.len();
+                }
+                closing_stack.push(close);
+                i += open.len();
+            } else if let Some(&close) = closing_stack.last()
+                && rest.starts_with(close)
+            {
+                closing_stack.pop();
+                if closing_stack.is_empty() {
+                    out.push_str(&replace(&text[block_from..i]));
+                    kept_from = i + close.len();
+                }
+                i += close.len();
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        if closing_stack.is_empty() {
+            out.push_str(&text[kept_from..]);
+        }
+
+        out
+    }
+
+    fn decode_entities(text: &str) -> String {
+        HTML_ENTITIES
+            .iter()
+            .fold(text.to_owned(), |text, (entity, decoded)| {
+                text.replace(entity, decoded)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gutenberg_keeps_only_the_book() {
+        let book = "The Project Gutenberg eBook of Tales\r\n\r\nThis eBook is for the use of anyone anywhere.\r\n\r\n*** START OF THE PROJECT GUTENBERG EBOOK TALES ***\r\n\r\nProduced by Jane Doe.\r\n\r\nCONTENTS\r\n\r\nI. The Road\r\nII. The Village\r\n\r\nCHAPTER I\r\n\r\nThe old man walked slowly down the long and dusty road to the village,\r\nthinking of the days long gone.\r\n\r\n[Illustration: The road]\r\n\r\nHe arrived at dusk.\r\n\r\n*** END OF THE PROJECT GUTENBERG EBOOK TALES ***\r\n\r\nSection 1. General Terms of Use of the Project Gutenberg License.\r\n";
+
+        assert_eq!(
+            Presets::gutenberg(book),
+            "The old man walked slowly down the long and dusty road to the village, thinking of the days long gone.\n\nHe arrived at dusk."
+        );
+    }
+
+    #[test]
+    fn gutenberg_without_markers_keeps_the_whole_text() {
+        assert_eq!(
+            Presets::gutenberg("First line\nof a paragraph.\n\nAnother one."),
+            "First line of a paragraph.\n\nAnother one."
+        );
+    }
+
+    #[test]
+    fn wikitext_keeps_only_the_prose() {
+        let article = "{{Infobox city|name=Paris|motto={{lang|la|Fluctuat}}}}\n'''Paris''' is the [[capital city|capital]] of [[France]].<ref>{{cite web|url=x}}</ref> It has [https://example.com many] museums &amp; parks.\n[[File:Paris.jpg|thumb|The city]]\nThe Eiffel Tower at night\n\n== History ==\n* A list item.\n{| class=\"wikitable\"\n| cell\n|}\nThe city is old.<!-- a comment -->\nIt grew along the river.\n[[Category:Cities]]";
+
+        assert_eq!(
+            Presets::wikitext(article),
+            "Paris is the capital of France. It has many museums & parks.\n\nThe Eiffel Tower at night\n\nThe city is old.\nIt grew along the river."
+        );
+    }
+}
@@ -0,0 +1,761 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Cursor, Read, Seek};
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use quick_xml::Reader;
+use quick_xml::events::Event as XmlEvent;
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node, Selector};
+use zip::ZipArchive;
+
+use crate::cli::Preset;
+use crate::constants::{
+    EPUB_DROPPED_FILE_PATTERN, EPUB_DROPPED_TAGS, EPUB_DROPPED_TYPES, HTML_BLOCK_TAGS,
+    HTML_DROPPED_ROLES, HTML_DROPPED_TAGS, MEDIA_FILE_EXTENSIONS, PDF_PAGE_NUMBER_PATTERN,
+    PDF_RUNNING_LINE_MIN_PAGES, PDF_RUNNING_LINE_MIN_SHARE, PDF_RUNNING_LINE_SPAN,
+    SUBTITLE_ANNOTATION_PATTERN, SUBTITLE_MARKUP_PATTERN, SUBTITLE_SPEAKER_PATTERN,
+};
+use crate::presets::Presets;
+use crate::segmenter::{Segmenter, SentenceStream};
+use crate::util::Util;
+
+/// The formats of training input that need their own reader, detected from the file extension. Anything else is read as plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Srt,
+    WebVtt,
+    Ass,
+    Epub,
+    Html,
+    Markdown,
+    Pdf,
+    /// Only has a reader of its own with the Wikipedia preset (dumps), read as plain text otherwise.
+    Xml,
+}
+
+impl Format {
+    pub fn from_path(filename: &str) -> Self {
+        let extension = Path::new(filename)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "srt" => Self::Srt,
+            "vtt" => Self::WebVtt,
+            "ass" | "ssa" => Self::Ass,
+            "epub" => Self::Epub,
+            "html" | "htm" | "xhtml" => Self::Html,
+            "md" | "markdown" | "mdown" | "mkd" => Self::Markdown,
+            "pdf" => Self::Pdf,
+            "xml" => Self::Xml,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// A single subtitle cue: when it starts and what is said.
+struct Cue {
+    start: String,
+    text: String,
+}
+
+pub struct Readers;
+
+impl Readers {
+    /// Reads the sentences of an input in the given format, unless a preset is given: presets know what their sources look like themselves, and read them as text. Plain text and Wikipedia dumps are streamed, so that they can be as big as they like; the other formats are read whole.
+    /// @param lang - the language of the input, see `Segmenter::new()`.
+    /// @param path - the path of the input, if it's a file.
+    /// @param on_sentence - called with every sentence and its location in the input as soon as it's read, the location being empty for the formats that have no such notion.
+    pub fn sentences(
+        reader: &mut dyn BufRead,
+        format: Format,
+        preset: Preset,
+        lang: &str,
+        path: Option<&Path>,
+        on_sentence: &mut dyn FnMut(String, String) -> Result<()>,
+    ) -> Result<()> {
+        let text = match (preset, format) {
+            (Preset::Wikipedia, Format::Xml) => {
+                Presets::wikipedia_dump(reader, |article| {
+                    Self::emit(Util::sentences_from_text(&article, lang), on_sentence)
+                })?;
+                return Ok(());
+            }
+            (Preset::Plain, Format::Text | Format::Xml) => {
+                for sentence in SentenceStream::new(reader, lang) {
+                    on_sentence(sentence?, String::new())?;
+                }
+                return Ok(());
+            }
+            (Preset::Plain, Format::Srt | Format::WebVtt | Format::Ass) => {
+                let located =
+                    Self::subtitle_sentences(&Self::read_text(reader)?, format, lang, path);
+                return Self::emit_located(located, on_sentence);
+            }
+            (Preset::Plain, Format::Epub) => {
+                let located = Self::epub_sentences(Self::read_bytes(reader)?, lang)?;
+                return Self::emit_located(located, on_sentence);
+            }
+            (Preset::Plain, Format::Pdf) => Self::pdf_text(&Self::read_bytes(reader)?)?,
+            (Preset::Plain, Format::Html | Format::Markdown) => {
+                let text = Self::read_text(reader)?;
+                let blocks = if format == Format::Html {
+                    Self::html_document_blocks(&text)
+                } else {
+                    Self::markdown_blocks(&text)
+                };
+                blocks.join("\n\n")
+            }
+            (preset, _) => Presets::apply(preset, Self::read_text(reader)?),
+        };
+
+        Self::emit(Util::sentences_from_text(&text, lang), on_sentence)
+    }
+
+    fn emit(
+        sentences: Vec<String>,
+        on_sentence: &mut dyn FnMut(String, String) -> Result<()>,
+    ) -> Result<()> {
+        for sentence in sentences {
+            on_sentence(sentence, String::new())?;
+        }
+
+        Ok(())
+    }
+
+    fn emit_located(
+        located: Vec<(String, String)>,
+        on_sentence: &mut dyn FnMut(String, String) -> Result<()>,
+    ) -> Result<()> {
+        for (sentence, location) in located {
+            on_sentence(sentence, location)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_text(reader: &mut dyn BufRead) -> Result<String> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).context(
+            "Readers::read_text(): couldn't read the input as text, it has to be UTF-8 encoded.",
+        )?;
+
+        Ok(text)
+    }
+
+    fn read_bytes(reader: &mut dyn BufRead) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .context("Readers::read_bytes(): couldn't read the input.")?;
+
+        Ok(bytes)
+    }
+
+    /// Reads the sentences of a subtitle file. Cue numbers, timestamps, styling, sound descriptions and speaker labels are dropped, and cues that continue a sentence are merged with it.
+    /// @returns - pairs of (sentence, location), where the location is the start of the cue the sentence begins in, prefixed with the media file the subtitles belong to if there is one next to them.
+    pub fn subtitle_sentences(
+        text: &str,
+        format: Format,
+        lang: &str,
+        path: Option<&Path>,
+    ) -> Vec<(String, String)> {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let cues = match format {
+            Format::Ass => Self::ass_cues(&text),
+            _ => Self::timed_cues(&text),
+        };
+        let location_prefix = path
+            .and_then(Self::media_file_of)
+            .map(|media| format!("{} @ ", media))
+            .unwrap_or_default();
+
+        let mut sentences = vec![];
+        let mut segmenter = Segmenter::new(lang);
+        let mut pending_start = String::new();
+        for cue in cues {
+            let text = Self::clean_cue(&cue.text);
+            if text.is_empty() {
+                continue;
+            }
+            if !segmenter.has_pending() {
+                pending_start = cue.start.clone();
+            }
+
+            // NOTE: only the first finished sentence may have started in an earlier cue, the rest of them started in this one.
+            let finished = segmenter.push(&format!(" {}", text));
+            for (i, sentence) in finished.iter().enumerate() {
+                let start = if i == 0 { &pending_start } else { &cue.start };
+                sentences.push((sentence.clone(), format!("{}{}", location_prefix, start)));
+            }
+            if !finished.is_empty() {
+                pending_start = cue.start;
+            }
+        }
+        for sentence in segmenter.finish() {
+            sentences.push((sentence, format!("{}{}", location_prefix, pending_start)));
+        }
+
+        sentences
+    }
+
+    /// Parses SRT and WebVTT cues. Both are blocks separated by blank lines, with a `start --> end` timing line followed by the text. Blocks without a timing line (cue numbers aside, the WebVTT header, notes and styles) are skipped.
+    fn timed_cues(text: &str) -> Vec<Cue> {
+        text.split("\n\n")
+            .filter_map(|block| {
+                let mut lines = block.lines().skip_while(|l| !l.contains("-->"));
+                let start = lines.next()?.split("-->").next()?.trim().replace(',', ".");
+                let text = lines.collect::<Vec<&str>>().join("\n");
+
+                Some(Cue { start, text })
+            })
+            .collect()
+    }
+
+    /// Parses the `Dialogue:` lines of the `[Events]` section of an ASS/SSA file, using the section's `Format:` line to find the start and text fields.
+    fn ass_cues(text: &str) -> Vec<Cue> {
+        let mut fields: Vec<String> = vec![];
+        let mut in_events = false;
+        let mut cues = vec![];
+
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_events = line.eq_ignore_ascii_case("[events]");
+            } else if !in_events {
+                continue;
+            } else if let Some(format) = line.strip_prefix("Format:") {
+                fields = format.split(',').map(|f| f.trim().to_lowercase()).collect();
+            } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+                // NOTE: the text is always the last field and may contain commas itself.
+                let values: Vec<&str> = dialogue.splitn(fields.len().max(1), ',').collect();
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .position(|f| f == name)
+                        .and_then(|i| values.get(i))
+                        .map(|v| v.trim())
+                };
+                if let (Some(start), Some(text)) = (field("start"), field("text")) {
+                    cues.push(Cue {
+                        start: start.to_owned(),
+                        text: text
+                            .replace("\\N", "\n")
+                            .replace("\\n", "\n")
+                            .replace("\\h", " "),
+                    });
+                }
+            }
+        }
+
+        cues
+    }
+
+    /// Strips a cue's text down to what is said, joining its lines. Lines with music notes are lyrics rather than speech and are dropped.
+    fn clean_cue(text: &str) -> String {
+        text.lines()
+            .filter(|l| !l.contains('♪') && !l.contains('♫'))
+            .map(|l| {
+                let l = SUBTITLE_MARKUP_PATTERN.replace_all(l, "");
+                let l = SUBTITLE_ANNOTATION_PATTERN.replace_all(&l, "");
+                SUBTITLE_SPEAKER_PATTERN.replace(&l, "").trim().to_owned()
+            })
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Looks for a media file with the same name as the subtitles next to them, e.g. `movie.mkv` for `movie.srt` or `movie.en.srt`.
+    fn media_file_of(path: &Path) -> Option<String> {
+        let dir = path.parent()?;
+        let mut stem = path.file_stem()?.to_string_lossy().to_string();
+
+        loop {
+            for extension in MEDIA_FILE_EXTENSIONS {
+                let candidate = dir.join(format!("{}.{}", stem, extension));
+                if candidate.is_file() {
+                    return candidate
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string());
+                }
+            }
+            // NOTE: language suffixes such as `.en` are stripped one at a time.
+            stem = stem.rsplit_once('.')?.0.to_owned();
+        }
+    }
+
+    /// Reads the chapters of an EPUB book in their reading (spine) order. Front and back matter (cover, title page, table of contents, copyright etc.) and notes are dropped, both the whole chapters and the elements marked up as such. Every block element (paragraph, heading, list item...) of a chapter is split into sentences on its own.
+    /// @returns - pairs of (sentence, chapter), where the chapter is the first heading of the chapter file, or its title or name when it has none.
+    pub fn epub_sentences(bytes: Vec<u8>, lang: &str) -> Result<Vec<(String, String)>> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))
+            .context("Readers::epub_sentences(): the input is not a valid EPUB (zip) file.")?;
+
+        let container = Self::zip_text(&mut archive, "META-INF/container.xml")?;
+        let package_path = Self::xml_elements(&container, b"rootfile")?
+            .into_iter()
+            .find_map(|mut attributes| attributes.remove("full-path"))
+            .context("Readers::epub_sentences(): the EPUB container doesn't point to a package document.")?;
+        let package = Self::zip_text(&mut archive, &package_path)?;
+        let package_dir = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        // NOTE: the manifest maps item ids to files, the spine lists the ids in reading order, the guide marks front and back matter in EPUB 2 books.
+        let manifest: HashMap<String, HashMap<String, String>> =
+            Self::xml_elements(&package, b"item")?
+                .into_iter()
+                .filter_map(|attributes| Some((attributes.get("id")?.clone(), attributes)))
+                .collect();
+        let dropped_hrefs: HashSet<String> = Self::xml_elements(&package, b"reference")?
+            .into_iter()
+            .filter(|attributes| {
+                attributes
+                    .get("type")
+                    .is_some_and(|t| t != "text" && t != "bodymatter")
+            })
+            .filter_map(|mut attributes| attributes.remove("href"))
+            .map(|href| href.split('#').next().unwrap_or_default().to_owned())
+            .collect();
+
+        let mut sentences = vec![];
+        for itemref in Self::xml_elements(&package, b"itemref")? {
+            let Some(item) = itemref.get("idref").and_then(|id| manifest.get(id)) else {
+                continue;
+            };
+            let href = item.get("href").map_or("", String::as_str);
+            if itemref.get("linear").is_some_and(|l| l == "no")
+                || item
+                    .get("properties")
+                    .is_some_and(|p| p.split_whitespace().any(|p| p == "nav"))
+                || dropped_hrefs.contains(href)
+                || EPUB_DROPPED_FILE_PATTERN.is_match(href)
+            {
+                continue;
+            }
+
+            let path = Self::zip_path(package_dir, &percent_decode_str(href).decode_utf8_lossy());
+            let document = Html::parse_document(&Self::zip_text(&mut archive, &path)?);
+            let chapter = Self::chapter_title(&document).unwrap_or(path);
+
+            let blocks = Self::html_blocks(document.root_element(), &|e| {
+                EPUB_DROPPED_TAGS.contains(&e.name())
+                    || [e.attr("epub:type"), e.attr("role")]
+                        .into_iter()
+                        .flatten()
+                        .flat_map(str::split_whitespace)
+                        .any(|t| EPUB_DROPPED_TYPES.contains(&t.trim_start_matches("doc-")))
+            });
+            for sentence in Util::sentences_from_text(&blocks.join("\n\n"), lang) {
+                sentences.push((sentence, chapter.clone()));
+            }
+        }
+
+        Ok(sentences)
+    }
+
+    /// Extracts the text of a PDF page by page. Page numbers and running headers and footers (lines repeating at the edges of many pages, chapter titles and such) are dropped, then the lines are rejoined into paragraphs, undoing the hyphenation of words broken at line ends. Paragraphs, and so sentences, can continue onto the next page.
+    /// @returns - the text, with paragraphs separated by blank lines.
+    pub fn pdf_text(bytes: &[u8]) -> Result<String> {
+        // NOTE: the extractor panics on some malformed PDFs, which shouldn't take the whole training run down with it.
+        let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+            .map_err(|_| {
+                anyhow!("Readers::pdf_text(): the PDF extractor crashed, the file is probably malformed.")
+            })?
+            .context("Readers::pdf_text(): couldn't extract text from the input, is it a PDF?")?;
+
+        Ok(Self::join_pdf_pages(&pages))
+    }
+
+    /// Puts the text of the pages of a PDF back together, see `pdf_text()`.
+    fn join_pdf_pages(pages: &[String]) -> String {
+        // NOTE: blank lines at the edges of a page aren't paragraph breaks, as paragraphs often continue onto the next page.
+        let pages: Vec<Vec<&str>> = pages
+            .iter()
+            .map(|page| page.trim().lines().map(str::trim).collect::<Vec<&str>>())
+            .collect();
+
+        // NOTE: running lines are compared with their digits masked, so that "Chapter 3 · 41" and "Chapter 3 · 42" match.
+        let mask = |line: &str| line.replace(|c: char| c.is_ascii_digit(), "#");
+        let edge_lines = |page: &[&str]| {
+            let lines: Vec<&str> = page.iter().copied().filter(|l| !l.is_empty()).collect();
+            let top = lines.iter().take(PDF_RUNNING_LINE_SPAN);
+            let bottom = lines.iter().rev().take(PDF_RUNNING_LINE_SPAN);
+            top.chain(bottom)
+                .map(|l| mask(l))
+                .collect::<HashSet<String>>()
+        };
+        let mut edge_counts: HashMap<String, usize> = HashMap::new();
+        for page in &pages {
+            for line in edge_lines(page) {
+                *edge_counts.entry(line).or_insert(0) += 1;
+            }
+        }
+        let min_pages = PDF_RUNNING_LINE_MIN_PAGES
+            .max((pages.len() as f64 * PDF_RUNNING_LINE_MIN_SHARE).ceil() as usize);
+
+        let mut paragraphs: Vec<String> = vec![String::new()];
+        for page in &pages {
+            let edges = edge_lines(page);
+            for line in page {
+                let paragraph = paragraphs.last_mut().expect("never empty");
+                if line.is_empty() {
+                    if !paragraph.is_empty() {
+                        paragraphs.push(String::new());
+                    }
+                    continue;
+                }
+                let masked = mask(line);
+                if edges.contains(&masked)
+                    && (PDF_PAGE_NUMBER_PATTERN.is_match(line)
+                        || edge_counts.get(&masked).is_some_and(|&n| n >= min_pages))
+                {
+                    continue;
+                }
+
+                // NOTE: a hyphen after a letter at the end of a line is a word broken in two if the next line starts with a lowercase letter, otherwise it's the hyphen of a compound broken after it ("Jean-" + "Paul"), which is kept.
+                let hyphen_at_end = paragraph
+                    .strip_suffix('-')
+                    .and_then(|rest| rest.chars().last())
+                    .is_some_and(char::is_alphabetic);
+                match line.chars().next() {
+                    Some(c) if hyphen_at_end && c.is_lowercase() => {
+                        paragraph.pop();
+                    }
+                    Some(c) if hyphen_at_end && c.is_alphanumeric() => {}
+                    _ if !paragraph.is_empty() => paragraph.push(' '),
+                    _ => {}
+                }
+                paragraph.push_str(line);
+            }
+        }
+
+        paragraphs.join("\n\n")
+    }
+
+    /// The text blocks of a web page. Only the main content is read if the page marks it, navigation, forms, tables, code and hidden elements are dropped.
+    fn html_document_blocks(text: &str) -> Vec<String> {
+        let document = Html::parse_document(text);
+        let main = Selector::parse("main, article, [role=main]").expect("valid selector");
+        let root = document
+            .select(&main)
+            .next()
+            .unwrap_or(document.root_element());
+
+        Self::html_blocks(root, &|e| {
+            HTML_DROPPED_TAGS.contains(&e.name())
+                || e.attr("role")
+                    .is_some_and(|r| HTML_DROPPED_ROLES.contains(&r))
+                || e.attr("hidden").is_some()
+                || e.attr("aria-hidden") == Some("true")
+        })
+    }
+
+    /// The text blocks of a Markdown document: every paragraph, heading, list item etc. is a block of its own. Code blocks, tables, images, raw HTML, footnotes and front matter are dropped, inline markup is reduced to its text.
+    fn markdown_blocks(text: &str) -> Vec<String> {
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+            | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+        let mut blocks = vec![String::new()];
+        // NOTE: how deep inside of a dropped element the parser currently is.
+        let mut dropped_depth = 0;
+
+        for event in Parser::new_ext(text, options) {
+            match event {
+                Event::Start(tag) => {
+                    if dropped_depth > 0
+                        || matches!(
+                            tag,
+                            Tag::CodeBlock(_)
+                                | Tag::HtmlBlock
+                                | Tag::Table(_)
+                                | Tag::Image { .. }
+                                | Tag::FootnoteDefinition(_)
+                                | Tag::MetadataBlock(_)
+                        )
+                    {
+                        dropped_depth += 1;
+                    } else if !matches!(
+                        tag,
+                        Tag::Emphasis
+                            | Tag::Strong
+                            | Tag::Strikethrough
+                            | Tag::Superscript
+                            | Tag::Subscript
+                            | Tag::Link { .. }
+                    ) {
+                        blocks.push(String::new());
+                    }
+                }
+                Event::End(tag) => {
+                    if dropped_depth > 0 {
+                        dropped_depth -= 1;
+                    } else if !matches!(
+                        tag,
+                        TagEnd::Emphasis
+                            | TagEnd::Strong
+                            | TagEnd::Strikethrough
+                            | TagEnd::Superscript
+                            | TagEnd::Subscript
+                            | TagEnd::Link
+                    ) {
+                        blocks.push(String::new());
+                    }
+                }
+                Event::Text(text) | Event::Code(text) if dropped_depth == 0 => {
+                    blocks.last_mut().expect("never empty").push_str(&text)
+                }
+                Event::SoftBreak | Event::HardBreak if dropped_depth == 0 => {
+                    blocks.last_mut().expect("never empty").push(' ')
+                }
+                _ => {}
+            }
+        }
+
+        blocks
+            .iter()
+            .map(|b| b.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .filter(|b| !b.is_empty())
+            .collect()
+    }
+
+    /// Collects the text of an HTML element, split into blocks at every block element (see `HTML_BLOCK_TAGS`), with whitespace collapsed. Elements for which `dropped` returns true are skipped together with their content.
+    pub fn html_blocks(root: ElementRef, dropped: &dyn Fn(&Element) -> bool) -> Vec<String> {
+        let mut blocks = vec![String::new()];
+        Self::collect_html_blocks(root, dropped, &mut blocks);
+
+        blocks
+            .iter()
+            .map(|b| b.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .filter(|b| !b.is_empty())
+            .collect()
+    }
+
+    fn collect_html_blocks(
+        element: ElementRef,
+        dropped: &dyn Fn(&Element) -> bool,
+        blocks: &mut Vec<String>,
+    ) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => blocks.last_mut().expect("never empty").push_str(text),
+                // NOTE: a line break is only whitespace, the lines of a paragraph or a verse are still a part of the same sentences.
+                Node::Element(e) if e.name() == "br" => {
+                    blocks.last_mut().expect("never empty").push(' ')
+                }
+                Node::Element(e) if !dropped(e) => {
+                    let is_block = HTML_BLOCK_TAGS.contains(&e.name());
+                    if is_block {
+                        blocks.push(String::new());
+                    }
+                    if let Some(child) = ElementRef::wrap(child) {
+                        Self::collect_html_blocks(child, dropped, blocks);
+                    }
+                    if is_block {
+                        blocks.push(String::new());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn chapter_title(document: &Html) -> Option<String> {
+        ["h1, h2, h3", "title"].into_iter().find_map(|selector| {
+            let selector = Selector::parse(selector).expect("valid selector");
+            document
+                .select(&selector)
+                .map(|e| e.text().collect::<Vec<&str>>().join(" "))
+                .map(|t| t.split_whitespace().collect::<Vec<&str>>().join(" "))
+                .find(|t| !t.is_empty())
+        })
+    }
+
+    /// Resolves a path relative to a directory of a zip archive into the name of the file in the archive, dropping the `.` segments and going up a directory on the `..` ones (`OEBPS` and `../Text/ch2.xhtml` are `Text/ch2.xhtml`).
+    fn zip_path(dir: &str, href: &str) -> String {
+        let mut segments: Vec<&str> = vec![];
+        for segment in dir.split('/').chain(href.split('/')) {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                }
+                segment => segments.push(segment),
+            }
+        }
+
+        segments.join("/")
+    }
+
+    fn zip_text<R: Read + Seek>(archive: &mut ZipArchive<R>, path: &str) -> Result<String> {
+        let mut text = String::new();
+        archive
+            .by_name(path)
+            .context(format!(
+                "Readers::zip_text(): the EPUB has no `{}` in it.",
+                path
+            ))?
+            .read_to_string(&mut text)
+            .context(format!(
+                "Readers::zip_text(): `{}` in the EPUB is not valid UTF-8.",
+                path
+            ))?;
+
+        Ok(text)
+    }
+
+    /// The attributes of every `tag` element in an XML document, keyed by their local names.
+    fn xml_elements(xml: &str, tag: &[u8]) -> Result<Vec<HashMap<String, String>>> {
+        let mut reader = Reader::from_str(xml);
+        let mut elements = vec![];
+
+        loop {
+            match reader.read_event()? {
+                XmlEvent::Start(e) | XmlEvent::Empty(e) if e.local_name().as_ref() == tag => {
+                    let mut attributes = HashMap::new();
+                    for attribute in e.attributes() {
+                        let attribute = attribute?;
+                        attributes.insert(
+                            String::from_utf8_lossy(attribute.key.local_name().as_ref())
+                                .to_string(),
+                            attribute.unescape_value()?.to_string(),
+                        );
+                    }
+                    elements.push(attributes);
+                }
+                XmlEvent::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(sentences: Vec<(String, String)>) -> Vec<String> {
+        sentences
+            .into_iter()
+            .map(|(sentence, _)| sentence)
+            .collect()
+    }
+
+    #[test]
+    fn cues_are_cleaned_down_to_speech() {
+        assert_eq!(
+            Readers::clean_cue("<i>- JOHN: Hello there.</i>\n[DOOR CLOSES]\n♪ la la la ♪"),
+            "Hello there."
+        );
+        assert_eq!(
+            Readers::clean_cue("{\\an8}(laughs) It's <font color=\"red\">fine</font>."),
+            "It's fine."
+        );
+    }
+
+    #[test]
+    fn srt_cues_continuing_a_sentence_are_merged() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:03,000\r\nI think that we\r\n\r\n2\r\n00:00:03,500 --> 00:00:05,000\r\nshould leave now. You agree?\r\n";
+
+        assert_eq!(
+            Readers::subtitle_sentences(srt, Format::Srt, "English", None),
+            [
+                (
+                    "I think that we should leave now.".to_owned(),
+                    "00:00:01.000".to_owned()
+                ),
+                ("You agree?".to_owned(), "00:00:03.500".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn vtt_header_and_notes_are_skipped() {
+        let vtt = "WEBVTT\n\nNOTE a comment\n\n00:01.000 --> 00:02.000 align:start\n<v Mary>We walked home together.</v>\n";
+
+        assert_eq!(
+            texts(Readers::subtitle_sentences(
+                vtt,
+                Format::WebVtt,
+                "English",
+                None
+            )),
+            ["We walked home together."]
+        );
+    }
+
+    #[test]
+    fn ass_dialogue_text_is_read_with_its_commas() {
+        let ass = "[Script Info]\nTitle: Test\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\i1}Well, we walked\\Nhome together.{\\i0}\n";
+
+        assert_eq!(
+            Readers::subtitle_sentences(ass, Format::Ass, "English", None),
+            [(
+                "Well, we walked home together.".to_owned(),
+                "0:00:01.00".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn pdf_words_broken_at_line_ends_are_rejoined() {
+        let pages = [
+            "The old man walked slow-\nly down the road with Jean-\nPaul.\n\nA new para-\ngraph starts".to_owned(),
+            "here and ends here.".to_owned(),
+        ];
+
+        assert_eq!(
+            Readers::join_pdf_pages(&pages),
+            "The old man walked slowly down the road with Jean-Paul.\n\nA new paragraph starts here and ends here."
+        );
+    }
+
+    #[test]
+    fn pdf_page_numbers_and_running_lines_are_dropped() {
+        let pages: Vec<String> = ["one", "two", "three", "four"]
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                format!(
+                    "A Book Title\nChapter {}\nPage {} starts,\ngoes on\nand {} ends.\n{}",
+                    i + 1,
+                    n,
+                    n,
+                    i + 1
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            Readers::join_pdf_pages(&pages),
+            ["one", "two", "three", "four"]
+                .map(|n| format!("Page {} starts, goes on and {} ends.", n, n))
+                .join(" ")
+        );
+    }
+
+    #[test]
+    fn epub_paths_are_resolved() {
+        assert_eq!(
+            Readers::zip_path("OEBPS/pkg", "../Text/./ch1.xhtml"),
+            "OEBPS/Text/ch1.xhtml"
+        );
+        assert_eq!(Readers::zip_path("", "ch1.xhtml"), "ch1.xhtml");
+    }
+
+    #[test]
+    fn line_breaks_are_whitespace() {
+        let document =
+            Html::parse_document("<p>Heading-less text<br/>continues on.</p><p>Next.</p>");
+
+        assert_eq!(
+            Readers::html_blocks(document.root_element(), &|_| false),
+            ["Heading-less text continues on.", "Next."]
+        );
+    }
+}
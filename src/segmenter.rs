@@ -0,0 +1,330 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::BufRead;
+
+use anyhow::{Result, anyhow};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::constants::{
+    ABBREVIATIONS, CAPITAL_LETTER_WORDS, PARAGRAPH_BREAK_PATTERN, SEGMENTER_MAX_PENDING_BYTES,
+    SENTENCE_EXTRA_BREAK_PATTERN,
+};
+use crate::util::Util;
+
+/// Splits text into sentences incrementally: the text can be pushed in arbitrary pieces, and only the sentences that are known to be complete are split off, the rest is held on to until more text (or the end of it) comes.
+/// Sentence boundaries are found with the Unicode sentence boundary rules (UAX #29), so decimals (`3.14`), acronyms (`U.S.A.`), `...` followed by a lowercase word and terminators of other scripts (`。`, `؟`) are all handled, and closing quotes stay with their sentence. On top of that:
+/// - blank lines are hard sentence boundaries: a sentence never continues into the next paragraph, so that headings, list items and the like don't get glued to whatever follows them;
+/// - a sentence never ends after an abbreviation of the language (`Dr.`, `e.g.`, or `E.g.` starting a sentence, see `data/abbreviations`) or an initial (`J. R. R. Tolkien`), unless the capital letter is a word of the language (`I.`);
+/// - a semicolon, or a `…` followed by a capitalized word, does end one.
+#[derive(Debug)]
+pub struct Segmenter {
+    pending: String,
+    abbreviations: Option<&'static HashSet<&'static str>>,
+    capital_letter_words: &'static [&'static str],
+}
+
+impl Segmenter {
+    /// @param lang - the language of the text, which picks the abbreviations and the capital letters that aren't initials. Languages without a list of them are only segmented by the Unicode rules.
+    pub fn new(lang: &str) -> Self {
+        Self {
+            pending: String::new(),
+            abbreviations: ABBREVIATIONS.get(lang.to_lowercase().as_str()),
+            capital_letter_words: CAPITAL_LETTER_WORDS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(lang))
+                .map_or(&[], |(_, words)| *words),
+        }
+    }
+
+    /// Adds the next piece of the text.
+    /// @returns - the sentences completed by it.
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        self.pending.push_str(text);
+        let mut sentences = vec![];
+
+        if let Some(last_break) = PARAGRAPH_BREAK_PATTERN.find_iter(&self.pending).last() {
+            for paragraph in PARAGRAPH_BREAK_PATTERN.split(&self.pending[..last_break.start()]) {
+                sentences.extend(self.split(paragraph, true).0);
+            }
+            self.pending.drain(..last_break.end());
+        }
+
+        loop {
+            let (complete, consumed) = self.split(&self.pending, false);
+            sentences.extend(complete);
+            self.pending.drain(..consumed);
+            if self.pending.len() <= SEGMENTER_MAX_PENDING_BYTES {
+                break;
+            }
+
+            // NOTE: cut at the last whitespace within the limit, so that the words around the cut stay whole, and the cut falls at the same place however the text was pushed.
+            let limit = self
+                .pending
+                .floor_char_boundary(SEGMENTER_MAX_PENDING_BYTES);
+            let cut = match self.pending[..limit].rfind(char::is_whitespace) {
+                Some(cut) if cut > 0 => cut,
+                _ => limit,
+            };
+            sentences.extend(self.split(&self.pending[..cut], true).0);
+            self.pending.drain(..cut);
+        }
+
+        sentences
+    }
+
+    /// Whether there's a started sentence waiting for the rest of the text.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.trim().is_empty()
+    }
+
+    /// Ends the text: whatever is left of it are the last sentences.
+    pub fn finish(self) -> Vec<String> {
+        self.split(&self.pending, true).0
+    }
+
+    /// Splits a paragraph (or the start of one) into sentences.
+    /// @param whole - whether the paragraph is complete. If not, only the sentences followed by a letter are split off: the text that is still to come can change the boundaries before that (`3.` + `14`, `end. ` + `and`).
+    /// @returns - the sentences, and the byte length of the text they were split from.
+    fn split(&self, paragraph: &str, whole: bool) -> (Vec<String>, usize) {
+        // NOTE: line breaks inside a paragraph are just spaces, but UAX #29 takes them for paragraph separators. Replacing them byte for byte keeps the offsets of the boundaries the same in both texts.
+        let flat = paragraph.replace(['\r', '\n'], " ");
+        let settled = if whole {
+            flat.len()
+        } else {
+            flat.rfind(char::is_alphabetic).unwrap_or(0)
+        };
+        let mut sentences = vec![];
+        let mut consumed = 0;
+
+        for end in self.boundaries(&flat) {
+            if end > settled {
+                break;
+            }
+
+            let piece = &paragraph[consumed..end];
+            if let Some(sentence) =
+                Util::clean_sentence(&piece.replace("\r\n", " ").replace("\n", " "))
+            {
+                sentences.push(sentence);
+            }
+            consumed = end;
+        }
+
+        (sentences, consumed)
+    }
+
+    /// Finds where the sentences of a single-line text end.
+    /// @returns - the byte offsets of the ends, the last one being the end of the text.
+    fn boundaries(&self, text: &str) -> Vec<usize> {
+        let mut ends = vec![];
+        for (start, sentence) in text.split_sentence_bound_indices() {
+            for captures in SENTENCE_EXTRA_BREAK_PATTERN.captures_iter(sentence) {
+                if let Some(extra) = captures.get(1).or_else(|| captures.get(2)) {
+                    ends.push(start + extra.end());
+                }
+            }
+
+            let end = start + sentence.len();
+            let sentence_start = ends.last().copied().unwrap_or(0);
+            if ends.last() != Some(&end)
+                && (end == text.len() || !self.ends_with_abbreviation(&text[sentence_start..end]))
+            {
+                ends.push(end);
+            }
+        }
+
+        ends
+    }
+
+    /// Whether a text the Unicode rules took for a sentence ends with an abbreviation or an initial instead, and so continues into the next one.
+    /// @param sentence - the text from the end of the previous sentence.
+    fn ends_with_abbreviation(&self, sentence: &str) -> bool {
+        let mut words = sentence.split_whitespace().rev();
+        let last_word = words.next().unwrap_or("");
+        let starts_sentence = words.next().is_none();
+        let Some(word) = last_word.strip_suffix('.') else {
+            return false;
+        };
+        let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+
+        let mut chars = word.chars();
+        let is_initial = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase())
+            && !self.capital_letter_words.contains(&word);
+        // NOTE: the abbreviations are matched as they're written, so that `No.` isn't `no.` and `Al.` isn't `al.`, but a lowercase one is also capitalized when it starts a sentence (`Cf.`, `E.g.`).
+        let mut letters = word.chars();
+        let decapitalized: String = letters
+            .next()
+            .filter(|_| starts_sentence)
+            .map(|first| first.to_lowercase().chain(letters).collect())
+            .unwrap_or_default();
+        is_initial
+            || self.abbreviations.is_some_and(|abbreviations| {
+                abbreviations.contains(word) || abbreviations.contains(decapitalized.as_str())
+            })
+    }
+}
+
+/// Streams the sentences of a UTF-8 text from a buffered reader, see `Segmenter`. Only the reader's buffer and the unfinished sentence are ever held in memory, no matter how big the text is.
+pub struct SentenceStream<R> {
+    reader: R,
+    segmenter: Option<Segmenter>,
+    /// Bytes read but not decoded yet: a character split across two reads.
+    undecoded: Vec<u8>,
+    ready: VecDeque<String>,
+}
+
+impl<R: BufRead> SentenceStream<R> {
+    /// @param lang - the language of the text, see `Segmenter::new()`.
+    pub fn new(reader: R, lang: &str) -> Self {
+        Self {
+            reader,
+            segmenter: Some(Segmenter::new(lang)),
+            undecoded: vec![],
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Reads and segments the next piece of the text.
+    fn read_more(&mut self) -> Result<()> {
+        let Some(segmenter) = self.segmenter.as_mut() else {
+            return Ok(());
+        };
+
+        let buf = self.reader.fill_buf()?;
+        if buf.is_empty() {
+            if !self.undecoded.is_empty() {
+                return Err(Self::not_utf8());
+            }
+            self.ready.extend(
+                self.segmenter
+                    .take()
+                    .map(Segmenter::finish)
+                    .unwrap_or_default(),
+            );
+            return Ok(());
+        }
+        self.undecoded.extend_from_slice(buf);
+        let read = buf.len();
+        self.reader.consume(read);
+
+        let valid = match std::str::from_utf8(&self.undecoded) {
+            Ok(text) => text.len(),
+            // NOTE: an error without a length is an incomplete character at the end, the rest of it comes with the next read.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(Self::not_utf8()),
+        };
+        let text = std::str::from_utf8(&self.undecoded[..valid])?;
+        self.ready.extend(segmenter.push(text));
+        self.undecoded.drain(..valid);
+
+        Ok(())
+    }
+
+    fn not_utf8() -> anyhow::Error {
+        anyhow!(
+            "SentenceStream::read_more(): couldn't read the input as text, it has to be UTF-8 encoded."
+        )
+    }
+}
+
+impl<R: BufRead> Iterator for SentenceStream<R> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sentence) = self.ready.pop_front() {
+                return Some(Ok(sentence));
+            }
+            self.segmenter.as_ref()?;
+            if let Err(e) = self.read_more() {
+                self.segmenter = None;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str, lang: &str) -> Vec<String> {
+        let mut segmenter = Segmenter::new(lang);
+        let mut sentences = segmenter.push(text);
+        sentences.extend(segmenter.finish());
+        sentences
+    }
+
+    #[test]
+    fn abbreviations_dont_end_sentences() {
+        assert_eq!(
+            sentences("Mr. Smith met Dr. Brown. They talked.", "English"),
+            ["Mr. Smith met Dr. Brown.", "They talked."]
+        );
+        assert_eq!(
+            sentences("Er kam z.B. Montag. Dann ging er.", "German"),
+            ["Er kam z.B. Montag.", "Dann ging er."]
+        );
+    }
+
+    #[test]
+    fn abbreviations_are_matched_capitalized_at_the_start_of_a_sentence() {
+        assert_eq!(
+            sentences("It was long. Cf. Smith for more. He left.", "English"),
+            ["It was long.", "Cf. Smith for more.", "He left."]
+        );
+        // NOTE: `No` is only an abbreviation when it's written capitalized, and `al` only when it's written in lowercase, unless it starts a sentence.
+        assert_eq!(
+            sentences("I met my friend Al. He was nice.", "English"),
+            ["I met my friend Al.", "He was nice."]
+        );
+        assert_eq!(
+            sentences("He said no. Then he left.", "English"),
+            ["He said no.", "Then he left."]
+        );
+    }
+
+    #[test]
+    fn initials_dont_end_sentences() {
+        assert_eq!(
+            sentences("J. R. R. Tolkien wrote it. It sold well.", "English"),
+            ["J. R. R. Tolkien wrote it.", "It sold well."]
+        );
+    }
+
+    #[test]
+    fn capital_letter_words_end_sentences() {
+        assert_eq!(
+            sentences("It was I. Then we left.", "English"),
+            ["It was I.", "Then we left."]
+        );
+        // NOTE: other languages don't have the word, `I.` is an initial there.
+        assert_eq!(
+            sentences("Dat was I. Jansen.", "Dutch"),
+            ["Dat was I. Jansen."]
+        );
+    }
+
+    #[test]
+    fn decimals_and_paragraphs() {
+        assert_eq!(
+            sentences(
+                "Pi is 3.14 or so. Right.\n\nHeading\n\nText here.",
+                "English"
+            ),
+            ["Pi is 3.14 or so.", "Right.", "Heading", "Text here."]
+        );
+    }
+
+    #[test]
+    fn pushed_pieces_are_segmented_like_the_whole_text() {
+        let text = "Mr. Smith paid 3.14 dollars. It was I. Then we left; he stayed.";
+        let mut segmenter = Segmenter::new("English");
+        let mut pieced = vec![];
+        for piece in text.as_bytes().chunks(5) {
+            pieced.extend(segmenter.push(std::str::from_utf8(piece).unwrap()));
+        }
+        pieced.extend(segmenter.finish());
+
+        assert_eq!(pieced, sentences(text, "English"));
+    }
+}
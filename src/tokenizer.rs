@@ -0,0 +1,203 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::constants::{
+    ELISIONS, HYPHEN_CLITICS, HYPHEN_EUPHONICS, UNSPACED_SCRIPT_RANGES, WORD_APOSTROPHES,
+    WORD_HYPHENS,
+};
+
+/// Splits sentences into words. Frequency counting, sentence ranking and everything that looks sentences up by their words go through here, so that the words they see are always the same.
+/// Words are found with the Unicode word boundary rules (UAX #29), so punctuation never sticks to a word, contractions (`don't`) and numbers (`3.14`, `1,000`) stay whole, and `word,word` or `word—word` are two words. On top of that:
+/// - parts joined by a single hyphen are one word (`well-known`, `covid-19`), unless the parts after the first are pronouns of the language (`dit-il`, `va-t-on`, `dá-me`);
+/// - elided words of the language are split off the word they're glued to (`l'homme` is `l'` and `homme`);
+/// - `’` is the same apostrophe as `'`, and soft hyphens are dropped;
+/// - tokens without a letter (numbers, dates) aren't words.
+///
+/// CJK ideographs come out as a word each, there's no dictionary to tell which of them go together.
+#[derive(Debug, Clone, Copy)]
+pub struct Tokenizer {
+    elisions: &'static [&'static str],
+    clitics: &'static [&'static str],
+    euphonics: &'static [&'static str],
+}
+
+impl Tokenizer {
+    /// @param lang - the language of the sentences, which picks the elision and hyphenation rules. Languages without any rules only get the Unicode ones.
+    pub fn new(lang: &str) -> Self {
+        Self {
+            elisions: Self::rules(&ELISIONS, lang),
+            clitics: Self::rules(&HYPHEN_CLITICS, lang),
+            euphonics: Self::rules(&HYPHEN_EUPHONICS, lang),
+        }
+    }
+
+    /// Splits a sentence into its words, in the order they appear in it.
+    pub fn words(&self, sentence: &str) -> Vec<String> {
+        let mut words = vec![];
+        for compound in Self::compounds(sentence) {
+            let compound: String = compound
+                .chars()
+                .filter(|c| *c != '\u{00AD}')
+                .map(|c| {
+                    if WORD_APOSTROPHES.contains(&c) {
+                        '\''
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            for part in self.split_clitics(&compound) {
+                self.split_elisions(part, &mut words);
+            }
+        }
+
+        words
+    }
+
+    fn rules(table: &[(&str, &'static [&'static str])], lang: &str) -> &'static [&'static str] {
+        table
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(lang))
+            .map_or(&[], |(_, rules)| *rules)
+    }
+
+    /// Puts the UAX #29 word segments of a sentence back together into the words as they're written: the parts joined by a single hyphen, and the runs of a script written without spaces.
+    fn compounds(sentence: &str) -> Vec<&str> {
+        let mut compounds = vec![];
+        // NOTE: the byte range of the word being put together, and the end of a hyphen right after it, if there is one.
+        let mut current: Option<(usize, usize)> = None;
+        let mut hyphen_end: Option<usize> = None;
+
+        for (start, segment) in sentence.split_word_bound_indices() {
+            let end = start + segment.len();
+            if segment.chars().any(char::is_alphanumeric) {
+                match current {
+                    Some((word_start, word_end))
+                        if hyphen_end == Some(start)
+                            || (word_end == start
+                                && Self::is_unspaced(&sentence[..word_end], segment)) =>
+                    {
+                        current = Some((word_start, end));
+                    }
+                    _ => {
+                        compounds.extend(current.map(|(s, e)| &sentence[s..e]));
+                        current = Some((start, end));
+                    }
+                }
+                hyphen_end = None;
+            } else if Self::is_hyphen(segment)
+                && hyphen_end.is_none()
+                && current.is_some_and(|(_, word_end)| word_end == start)
+            {
+                hyphen_end = Some(end);
+            } else {
+                compounds.extend(current.take().map(|(s, e)| &sentence[s..e]));
+                hyphen_end = None;
+            }
+        }
+        compounds.extend(current.map(|(s, e)| &sentence[s..e]));
+
+        compounds
+    }
+
+    fn is_hyphen(segment: &str) -> bool {
+        let mut chars = segment.chars();
+        matches!((chars.next(), chars.next()), (Some(c), None) if WORD_HYPHENS.contains(&c))
+    }
+
+    /// Whether the text and the segment right after it are both written in a script without spaces between words, and so are a part of the same run.
+    fn is_unspaced(text: &str, segment: &str) -> bool {
+        let in_script = |c: Option<char>| {
+            c.is_some_and(|c| {
+                UNSPACED_SCRIPT_RANGES
+                    .iter()
+                    .any(|(first, last)| (*first..=*last).contains(&c))
+            })
+        };
+        in_script(text.chars().next_back()) && in_script(segment.chars().next())
+    }
+
+    /// Splits a hyphenated word into its parts if all of them but the first are pronouns (or euphonic letters, which are dropped), otherwise keeps it whole.
+    fn split_clitics<'a>(&self, compound: &'a str) -> Vec<&'a str> {
+        let parts: Vec<&str> = compound.split(WORD_HYPHENS).collect();
+        let is_clitic = |part: &str| self.clitics.contains(&part.to_lowercase().as_str());
+        let is_euphonic = |part: &str| self.euphonics.contains(&part.to_lowercase().as_str());
+
+        if parts.len() < 2
+            || !parts[1..]
+                .iter()
+                .all(|part| is_clitic(part) || is_euphonic(part))
+        {
+            return vec![compound];
+        }
+
+        let mut kept = vec![parts[0]];
+        kept.extend(parts[1..].iter().filter(|part| !is_euphonic(part)));
+        kept
+    }
+
+    /// Splits the elided words off the start of a word, then adds them and the rest of it to the words, if they have any letters in them.
+    fn split_elisions(&self, word: &str, words: &mut Vec<String>) {
+        let mut rest = word;
+        while let Some((elided, after)) = rest.split_once('\'')
+            && after.starts_with(char::is_alphabetic)
+            && self
+                .elisions
+                .iter()
+                .any(|elision| elision.eq_ignore_ascii_case(elided))
+        {
+            words.push(format!("{}'", elided));
+            rest = after;
+        }
+
+        if rest.chars().any(char::is_alphabetic) {
+            words.push(rest.to_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(sentence: &str, lang: &str) -> Vec<String> {
+        Tokenizer::new(lang).words(sentence)
+    }
+
+    #[test]
+    fn elisions_are_split_off() {
+        assert_eq!(
+            words("L'homme qu'il aime.", "French"),
+            ["L'", "homme", "qu'", "il", "aime"]
+        );
+        assert_eq!(words("L'homme", "English"), ["L'homme"]);
+    }
+
+    #[test]
+    fn hyphenated_clitics_are_split_off() {
+        assert_eq!(words("Que dit-il ?", "French"), ["Que", "dit", "il"]);
+        assert_eq!(words("Où va-t-on ?", "French"), ["Où", "va", "on"]);
+        assert_eq!(words("Un arc-en-ciel.", "French"), ["Un", "arc-en-ciel"]);
+    }
+
+    #[test]
+    fn hyphenated_words_stay_whole() {
+        assert_eq!(
+            words("A well-known covid-19 fact — true.", "English"),
+            ["A", "well-known", "covid-19", "fact", "true"]
+        );
+    }
+
+    #[test]
+    fn apostrophes_are_the_same() {
+        assert_eq!(words("I don't know.", "English"), ["I", "don't", "know"]);
+        assert_eq!(words("I don’t know.", "English"), ["I", "don't", "know"]);
+    }
+
+    #[test]
+    fn numbers_are_not_words() {
+        assert_eq!(
+            words("In 1999 it cost 3.14 or 1,000 dollars.", "English"),
+            ["In", "it", "cost", "or", "dollars"]
+        );
+    }
+}
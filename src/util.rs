@@ -1,30 +1,34 @@
 use std::borrow::Cow;
-use std::fs::read_to_string;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
 use blake3::Hasher;
 use regex::Regex;
 
 use crate::constants::{GENERIC_SENTENCE_GARBAGE_PATTERNS, REDB_LAYOUT_VERSION};
+use crate::segmenter::Segmenter;
 
 pub struct Util;
 
 impl Util {
-    pub fn sentences_from_file(filename: &str) -> Result<Vec<String>> {
-        let text = read_to_string(filename).context(format!(
-            "Util::sentences_from_file(): couldn't read text from {} into a String. Check if the file exists, then try again.",
-            filename
-        ))?;
-        let raw_sentences = text
-            .replace("\r\n", " ")
-            .replace("\n", " ")
-            .split_inclusive(&['.', '!', '?', ';'])
-            .filter(|s| s.chars().any(char::is_alphabetic))
-            .filter(|s| !s.is_empty())
-            .map(|s| Util::clean_token(s, &GENERIC_SENTENCE_GARBAGE_PATTERNS))
-            .collect::<Vec<String>>();
-
-        Ok(raw_sentences)
+    /// Splits a whole text into sentences, see `Segmenter`.
+    /// @param lang - the language of the text.
+    pub fn sentences_from_text(text: &str, lang: &str) -> Vec<String> {
+        let mut segmenter = Segmenter::new(lang);
+        let mut sentences = segmenter.push(text);
+        sentences.extend(segmenter.finish());
+        sentences
+    }
+
+    /// Cleans up a single split off sentence, or discards it if it has no letters in it.
+    pub fn clean_sentence(sentence: &str) -> Option<String> {
+        if sentence.chars().any(char::is_alphabetic) {
+            Some(Util::clean_token(
+                sentence,
+                &GENERIC_SENTENCE_GARBAGE_PATTERNS,
+            ))
+        } else {
+            None
+        }
     }
 
     /// Cleans a token by applying an array of regex patterns to it.
@@ -69,4 +73,38 @@ impl Util {
         hasher.update(&(word_bytes.len() as u32).to_le_bytes());
         hasher.update(word_bytes);
     }
+
+    /// Current time as a Unix timestamp in seconds.
+    pub fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Formats a Unix timestamp as a `YYYY-MM-DD HH:MM:SS UTC` date.
+    pub fn format_timestamp(secs: u64) -> String {
+        let days = (secs / 86400) as i64;
+        let rem = secs % 86400;
+        // NOTE: the days-to-civil-date conversion from http://howardhinnant.github.io/date_algorithms.html, the proleptic Gregorian calendar in eras of 400 years.
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60
+        )
+    }
 }